@@ -1,145 +1,496 @@
-use std::fmt::{self, Debug, Display, Formatter};
+use std::{
+    fmt::{self, Debug, Display, Formatter},
+    rc::Rc,
+};
 
-#[derive(Debug)]
-pub struct Assign<'a> {
-    pub id: &'a str,
+#[derive(Debug, Clone)]
+pub struct Assign {
+    pub id: String,
     pub constant: bool,
-    pub expression: Box<Statement<'a>>,
+    pub expression: Expression,
 }
 
-impl Display for Assign<'_> {
+impl Display for Assign {
     fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
         write!(format, "id: {}, constant: {}, expression: {}", self.id, self.constant, self.expression)
     }
 }
 
-#[derive(Debug)]
-pub enum Statement<'a> {
+#[derive(Debug, Clone)]
+pub enum Statement {
     Comment(String),
-    BooleanLiteral(bool),
-    Identifier(Identifier),
-    NumericLiteral(NumericLiteral),
-    Declaration(Assign<'a>),
-    Assign(Assign<'a>),
-    ArithmeticExpression(ArithmeticExpression),
-    CallExpression(CallExpression<'a>),
-    ObjectLiteral(Object<'a>),
-    Property(Property<'a>),
-    MemberExpression(MemberExpression<'a>),
-    // NoneLiteral,
-    // UnaryExpression,
-    // FunctionDeclaration,
+    Expression(Expression),
+    Declaration(Assign),
+    Assign(Assign),
+    MemberAssign(MemberAssign),
+    CompoundAssign(CompoundAssign),
+    FunctionDeclaration(FunctionDeclaration),
+    Return(Option<Expression>),
+    /// `{ ... }`, its declarations are only visible inside the braces.
+    Block(Vec<Statement>),
+    If(IfStatement),
+    While(WhileStatement),
+    For(ForStatement),
+    ForIn(ForInStatement),
+    Break,
+    Continue,
 }
 
-impl Display for Statement<'_> {
+impl Display for Statement {
     fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
         use self::Statement::*;
         match *self {
             Comment(ref val) => write!(format, "Comment: \t\t{}", val),
-            BooleanLiteral(ref val) => write!(format, "Boolean Literal: \t{}", val),
-            Identifier(ref val) => write!(format, "Identifier: \t{}", val),
-            NumericLiteral(ref val) => write!(format, "Numeric Literal: \t{}", val),
+            Expression(ref expr) => write!(format, "Expression: \t\t{}", expr),
             Declaration(ref assign) => write!(format, "Declaration: \t\t{}", assign),
             Assign(ref assign) => write!(format, "Assign: \t\t{}", assign),
-            ArithmeticExpression(ref expr) => write!(format, "Arithmetic Expression:  {}", expr),
-            CallExpression(ref call) => write!(format, "Call Expression: \t{}", call),
-            ObjectLiteral(ref val) => write!(format, "Object Literal: \t{}", val),
-            Property(ref val) => write!(format, "Property: \t\t{}", val),
-            MemberExpression(ref val) => write!(format, "Member Expression: \t{}", val),
+            MemberAssign(ref assign) => write!(format, "Member Assign: \t\t{}", assign),
+            CompoundAssign(ref assign) => write!(format, "Compound Assign: \t{}", assign),
+            FunctionDeclaration(ref val) => write!(format, "Function Declaration: \t{}", val),
+            Return(Some(ref val)) => write!(format, "Return: \t\t{}", val),
+            Return(None) => write!(format, "Return"),
+            Block(ref statements) => {
+                write!(format, "Block: \t\t\t")?;
+                write_block(format, statements)
+            }
+            If(ref val) => write!(format, "If: \t\t\t{}", val),
+            While(ref val) => write!(format, "While: \t\t{}", val),
+            For(ref val) => write!(format, "For: \t\t\t{}", val),
+            ForIn(ref val) => write!(format, "For In: \t\t{}", val),
+            Break => write!(format, "Break"),
+            Continue => write!(format, "Continue"),
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Oper {
     Add,
     Sub,
     Mul,
     Div,
+    FloorDiv,
     Mod,
+    Pow,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+    Eq,
+    NotEq,
+    /// `===`, true when both sides are the same value, see `Is`.
+    StrictEq,
+    StrictNotEq,
+    /// `a is b`, the same check as `===`.
+    Is,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+}
+
+impl Display for Oper {
+    fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
+        use self::Oper::*;
+        let symbol = match self {
+            Add => "+",
+            Sub => "-",
+            Mul => "*",
+            Div => "/",
+            FloorDiv => "//",
+            Mod => "%",
+            Pow => "**",
+            BitAnd => "&",
+            BitOr => "|",
+            BitXor => "^",
+            Shl => "<<",
+            Shr => ">>",
+            Eq => "==",
+            NotEq => "!=",
+            StrictEq => "===",
+            StrictNotEq => "!==",
+            Is => "is",
+            Lt => "<",
+            LtEq => "<=",
+            Gt => ">",
+            GtEq => ">=",
+        };
+        write!(format, "{}", symbol)
+    }
+}
+
+/// Operators whose right side is only evaluated when the left side does not decide the result.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogicalOper {
+    And,
+    Or,
+    /// `a ?? b` is `a` unless it is `null`.
+    Coalesce,
+}
+
+impl Display for LogicalOper {
+    fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            LogicalOper::And => write!(format, "&&"),
+            LogicalOper::Or => write!(format, "||"),
+            LogicalOper::Coalesce => write!(format, "??"),
+        }
+    }
 }
 
-pub enum ArithmeticExpression {
-    Value(f64),
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOper {
+    Neg,
+    Plus,
+    Not,
+    BitNot,
+}
+
+impl Display for UnaryOper {
+    fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            UnaryOper::Neg => write!(format, "-"),
+            UnaryOper::Plus => write!(format, "+"),
+            UnaryOper::Not => write!(format, "!"),
+            UnaryOper::BitNot => write!(format, "~"),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum Expression {
+    NumericLiteral(NumericLiteral),
+    StringLiteral(String),
+    BooleanLiteral(bool),
+    NullLiteral,
     Identifier(Identifier),
-    Add(Box<ArithmeticExpression>, Box<ArithmeticExpression>),
-    Sub(Box<ArithmeticExpression>, Box<ArithmeticExpression>),
-    Mul(Box<ArithmeticExpression>, Box<ArithmeticExpression>),
-    Div(Box<ArithmeticExpression>, Box<ArithmeticExpression>),
-    Mod(Box<ArithmeticExpression>, Box<ArithmeticExpression>),
-    Paren(Box<ArithmeticExpression>),
+    ArrayLiteral(Vec<Expression>),
+    ObjectLiteral(Object),
+    Member(MemberExpression),
+    Call(CallExpression),
+    Update(UpdateExpression),
+    Unary(UnaryOper, Box<Expression>),
+    Binary(Box<Expression>, Oper, Box<Expression>),
+    Logical(Box<Expression>, LogicalOper, Box<Expression>),
+    Conditional(ConditionalExpression),
+    Match(MatchExpression),
+    Paren(Box<Expression>),
 }
 
-impl Display for ArithmeticExpression {
+impl Display for Expression {
     fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
-        use self::ArithmeticExpression::*;
+        use self::Expression::*;
         match *self {
-            Value(val) => write!(format, "{}", val),
+            NumericLiteral(ref val) => write!(format, "{}", val),
+            StringLiteral(ref val) => write!(format, "{:?}", val),
+            BooleanLiteral(val) => write!(format, "{}", val),
+            NullLiteral => write!(format, "null"),
             Identifier(ref val) => write!(format, "{}", val),
-            Add(ref left, ref right) => write!(format, "{} + {}", left, right),
-            Sub(ref left, ref right) => write!(format, "{} - {}", left, right),
-            Mul(ref left, ref right) => write!(format, "{} * {}", left, right),
-            Div(ref left, ref right) => write!(format, "{} / {}", left, right),
-            Mod(ref left, ref right) => write!(format, "{} % {}", left, right),
+            ArrayLiteral(ref items) => write_list(format, items),
+            ObjectLiteral(ref val) => write!(format, "{}", val),
+            Member(ref val) => write!(format, "{}", val),
+            Call(ref val) => write!(format, "{}", val),
+            Update(ref val) => write!(format, "{}", val),
+            Unary(operator, ref expr) => write!(format, "{}{}", operator, expr),
+            Binary(ref left, operator, ref right) => write!(format, "{} {} {}", left, operator, right),
+            Logical(ref left, operator, ref right) => write!(format, "{} {} {}", left, operator, right),
+            Conditional(ref val) => write!(format, "{}", val),
+            Match(ref val) => write!(format, "{}", val),
             Paren(ref expr) => write!(format, "({})", expr),
         }
     }
 }
 
-impl Debug for ArithmeticExpression {
+impl Debug for Expression {
     fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
-        use self::ArithmeticExpression::*;
+        use self::Expression::*;
         match *self {
-            Value(val) => write!(format, "{}", val),
-            Identifier(ref val) => write!(format, "{}", val),
-            Add(ref left, ref right) => write!(format, "({:?} + {:?})", left, right),
-            Sub(ref left, ref right) => write!(format, "({:?} - {:?})", left, right),
-            Mul(ref left, ref right) => write!(format, "({:?} * {:?})", left, right),
-            Div(ref left, ref right) => write!(format, "({:?} / {:?})", left, right),
-            Mod(ref left, ref right) => write!(format, "({:?} % {:?})", left, right),
+            Unary(operator, ref expr) => write!(format, "({}{:?})", operator, expr),
+            Binary(ref left, operator, ref right) => write!(format, "({:?} {} {:?})", left, operator, right),
+            Logical(ref left, operator, ref right) => write!(format, "({:?} {} {:?})", left, operator, right),
+            Conditional(ref val) => write!(format, "({:?} ? {:?} : {:?})", val.condition, val.consequent, val.alternate),
             Paren(ref expr) => write!(format, "[{:?}]", expr),
+            ref expr => write!(format, "{}", expr),
         }
     }
 }
 
-#[derive(Debug)]
-pub struct CallExpression<'a> {
-    pub func: &'a str,
-    pub args: Vec<&'a str>,
+fn write_list(format: &mut Formatter<'_>, items: &[Expression]) -> fmt::Result {
+    write!(format, "[")?;
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            write!(format, ", ")?;
+        }
+        write!(format, "{}", item)?;
+    }
+    write!(format, "]")
 }
 
-impl Display for CallExpression<'_> {
+/// `condition ? consequent : alternate`
+#[derive(Debug, Clone)]
+pub struct ConditionalExpression {
+    pub condition: Box<Expression>,
+    pub consequent: Box<Expression>,
+    pub alternate: Box<Expression>,
+}
+
+impl Display for ConditionalExpression {
     fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
-        write!(format, "fn {}(", self.func)?;
+        write!(format, "{} ? {} : {}", self.condition, self.consequent, self.alternate)
+    }
+}
+
+/// `match value { 1 => "one", "x" | "y" => "letter", n => n }`, the first arm with a matching
+/// pattern gives the value.
+#[derive(Debug, Clone)]
+pub struct MatchExpression {
+    pub value: Box<Expression>,
+    pub arms: Vec<MatchArm>,
+}
+
+impl Display for MatchExpression {
+    fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
+        write!(format, "match {} {{", self.value)?;
+        for (i, arm) in self.arms.iter().enumerate() {
+            if i > 0 {
+                write!(format, ",")?;
+            }
+            write!(format, " {}", arm)?;
+        }
+        write!(format, " }}")
+    }
+}
+
+/// An arm matches when any of its `|` separated patterns does.
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+    pub patterns: Vec<Pattern>,
+    pub body: Expression,
+}
+
+impl Display for MatchArm {
+    fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
+        for (i, pattern) in self.patterns.iter().enumerate() {
+            if i > 0 {
+                write!(format, " | ")?;
+            }
+            write!(format, "{}", pattern)?;
+        }
+        write!(format, " => {}", self.body)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    /// A number, string, bool or `null` literal, matching values equal to it.
+    Literal(Expression),
+    /// `_` matches any value.
+    Wildcard,
+    /// A name matches any value and binds it for the arm body.
+    Binding(String),
+}
+
+impl Display for Pattern {
+    fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Pattern::Literal(literal) => write!(format, "{}", literal),
+            Pattern::Wildcard => write!(format, "_"),
+            Pattern::Binding(name) => write!(format, "{}", name),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CallExpression {
+    pub callee: Box<Expression>,
+    pub args: Vec<Expression>,
+}
+
+impl Display for CallExpression {
+    fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
+        write!(format, "{}(", self.callee)?;
         let mut i = 0;
         for v in &self.args {
             i += 1;
             write!(format, "{}", v)?;
-            if &i < &self.args.len() {
-                write!(format, ",")?;
+            if i < self.args.len() {
+                write!(format, ", ")?;
             }
         }
         write!(format, ")")
     }
 }
 
-#[derive(Debug)]
-pub struct MemberExpression<'a> {
-    pub object: &'a str,
-    pub property: &'a str,
+/// An assignment to a property or an array item, e.g. `obj.x = 5;` or `matrix[i][j] = 0;`.
+#[derive(Debug, Clone)]
+pub struct MemberAssign {
+    pub target: MemberExpression,
+    pub expression: Expression,
 }
-impl Display for MemberExpression<'_> {
+
+impl Display for MemberAssign {
+    fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
+        write!(format, "target: {}, expression: {}", self.target, self.expression)
+    }
+}
+
+/// `target op= expression`, e.g. `x += 1;` or `obj.count *= 2;`. The target is a variable or a
+/// member expression.
+#[derive(Debug, Clone)]
+pub struct CompoundAssign {
+    pub target: Expression,
+    pub operator: Oper,
+    pub expression: Expression,
+}
+
+impl Display for CompoundAssign {
+    fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
+        write!(format, "target: {}, operator: {}=, expression: {}", self.target, self.operator, self.expression)
+    }
+}
+
+/// `++x`, `x--` and the like, `operator` is `Add` for `++` and `Sub` for `--`. The prefix form
+/// evaluates to the updated value, the postfix form to the value before the update.
+#[derive(Debug, Clone)]
+pub struct UpdateExpression {
+    pub target: Box<Expression>,
+    pub operator: Oper,
+    pub prefix: bool,
+}
+
+impl Display for UpdateExpression {
     fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
-        write!(format, "object: {} - property: {}", self.object, self.property)
+        if self.prefix {
+            write!(format, "{}{}{}", self.operator, self.operator, self.target)
+        } else {
+            write!(format, "{}{}{}", self.target, self.operator, self.operator)
+        }
     }
 }
 
-#[derive(Debug)]
-pub struct Property<'a> {
-    pub key: &'a str,
-    pub value: Option<Box<Statement<'a>>>,
+#[derive(Debug, Clone)]
+pub struct FunctionDeclaration {
+    pub name: String,
+    pub params: Vec<String>,
+    /// Shared with the functions created from the declaration, so evaluating it does not copy the body.
+    pub body: Rc<Vec<Statement>>,
 }
-impl Display for Property<'_> {
+
+impl Display for FunctionDeclaration {
+    fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
+        write!(format, "name: {}, params: ({}), body: ", self.name, self.params.join(", "))?;
+        write_block(format, &self.body)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IfStatement {
+    pub condition: Expression,
+    pub consequent: Vec<Statement>,
+    /// The `else` branch, an `else if` is a single nested `If` statement.
+    pub alternate: Option<Vec<Statement>>,
+}
+
+impl Display for IfStatement {
+    fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
+        write!(format, "condition: {}, then: ", self.condition)?;
+        write_block(format, &self.consequent)?;
+        if let Some(alternate) = &self.alternate {
+            write!(format, " else: ")?;
+            write_block(format, alternate)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WhileStatement {
+    pub condition: Expression,
+    pub body: Vec<Statement>,
+}
+
+impl Display for WhileStatement {
+    fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
+        write!(format, "condition: {}, body: ", self.condition)?;
+        write_block(format, &self.body)
+    }
+}
+
+/// A C-style `for (init; condition; update) { ... }` loop, every part of the header is optional.
+#[derive(Debug, Clone)]
+pub struct ForStatement {
+    pub init: Option<Box<Statement>>,
+    pub condition: Option<Expression>,
+    pub update: Option<Box<Statement>>,
+    pub body: Vec<Statement>,
+}
+
+impl Display for ForStatement {
+    fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
+        match &self.init {
+            Some(init) => write!(format, "init: {}, ", init)?,
+            None => write!(format, "init: none, ")?,
+        }
+        match &self.condition {
+            Some(condition) => write!(format, "condition: {}, ", condition)?,
+            None => write!(format, "condition: none, ")?,
+        }
+        match &self.update {
+            Some(update) => write!(format, "update: {}, ", update)?,
+            None => write!(format, "update: none, ")?,
+        }
+        write!(format, "body: ")?;
+        write_block(format, &self.body)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ForInStatement {
+    pub id: String,
+    pub iterable: Expression,
+    pub body: Vec<Statement>,
+}
+
+impl Display for ForInStatement {
+    fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
+        write!(format, "id: {}, iterable: {}, body: ", self.id, self.iterable)?;
+        write_block(format, &self.body)
+    }
+}
+
+fn write_block(format: &mut Formatter<'_>, statements: &[Statement]) -> fmt::Result {
+    write!(format, "{{")?;
+    for v in statements {
+        write!(format, "\n\t\t{}", v)?;
+    }
+    write!(format, "\n\t}}")
+}
+
+/// `object.property` or `object[property]`. Only the bracket form is `computed`, its property is
+/// evaluated while the property of the dot form is the name itself.
+#[derive(Debug, Clone)]
+pub struct MemberExpression {
+    pub object: Box<Expression>,
+    pub property: Box<Expression>,
+    pub computed: bool,
+}
+impl Display for MemberExpression {
+    fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
+        if self.computed {
+            write!(format, "{}[{}]", self.object, self.property)
+        } else {
+            write!(format, "{}.{}", self.object, self.property)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Property {
+    pub key: String,
+    pub value: Option<Expression>,
+}
+impl Display for Property {
     fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
         if let Some(val) = &self.value {
             write!(format, "key: {} - value: {}", self.key, val)
@@ -149,20 +500,21 @@ impl Display for Property<'_> {
     }
 }
 
-#[derive(Debug)]
-pub struct Object<'a> {
-    pub properties: Vec<Property<'a>>,
+#[derive(Debug, Clone)]
+pub struct Object {
+    pub properties: Vec<Property>,
 }
-impl Display for Object<'_> {
+impl Display for Object {
     fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
-        write!(format, "{{\n")?;
+        writeln!(format, "{{")?;
         for v in &self.properties {
-            write!(format, "\t{}\n", v)?;
+            writeln!(format, "\t{}", v)?;
         }
         write!(format, "}}")
     }
 }
 
+#[derive(Clone)]
 pub struct Identifier {
     pub id: String,
 }
@@ -179,18 +531,24 @@ impl Debug for Identifier {
     }
 }
 
-pub struct NumericLiteral {
-    pub value: f64,
+/// A number literal, `10` is an int while `10.0` and `1e3` are floats.
+#[derive(Clone, Copy)]
+pub enum NumericLiteral {
+    Int(i64),
+    Float(f64),
 }
 
 impl Display for NumericLiteral {
     fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
-        write!(format, "{}", self.value)
+        match self {
+            NumericLiteral::Int(val) => write!(format, "{}", val),
+            NumericLiteral::Float(val) => write!(format, "{:?}", val),
+        }
     }
 }
 
 impl Debug for NumericLiteral {
     fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
-        write!(format, "{}", self.value)
+        write!(format, "{}", self)
     }
 }
@@ -0,0 +1,218 @@
+use std::fmt::{self, Display, Formatter};
+
+use nom::{
+    error::{ContextError, ErrorKind, FromExternalError, ParseError as NomParseError},
+    Offset,
+};
+
+pub type IResult<I, O> = nom::IResult<I, O, Error<I>>;
+
+/// Error type threaded through the nom parsers. It remembers the furthest position reached and the
+/// tokens that were expected there, so the reported error points at the real problem instead of
+/// the start of the statement.
+#[derive(Debug, PartialEq)]
+pub struct Error<I> {
+    pub input: I,
+    pub expected: Vec<&'static str>,
+}
+
+impl<'a> Error<&'a str> {
+    pub fn expected(input: &'a str, token: &'static str) -> Self {
+        Self {
+            input,
+            expected: vec![token],
+        }
+    }
+}
+
+impl<'a> NomParseError<&'a str> for Error<&'a str> {
+    fn from_error_kind(input: &'a str, _kind: ErrorKind) -> Self {
+        Self { input, expected: vec![] }
+    }
+
+    fn append(_input: &'a str, _kind: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    fn or(mut self, other: Self) -> Self {
+        // Inputs can be sub-slices of the source (e.g. the text before a `;`), so positions are
+        // compared by address rather than by remaining length.
+        let position = self.input.as_ptr();
+        let other_position = other.input.as_ptr();
+
+        if position > other_position {
+            return self;
+        }
+        if position < other_position {
+            return other;
+        }
+
+        self.expected.extend(other.expected);
+        self
+    }
+}
+
+impl<'a> ContextError<&'a str> for Error<&'a str> {
+    fn add_context(_input: &'a str, ctx: &'static str, mut other: Self) -> Self {
+        if other.expected.is_empty() {
+            other.expected.push(ctx);
+        }
+        other
+    }
+}
+
+impl<'a, E> FromExternalError<&'a str, E> for Error<&'a str> {
+    fn from_external_error(input: &'a str, kind: ErrorKind, _e: E) -> Self {
+        Self::from_error_kind(input, kind)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// Byte offset of the error in the source.
+    pub offset: usize,
+    /// 1-based line of the error.
+    pub line: usize,
+    /// 1-based column of the error, counted in characters.
+    pub column: usize,
+    /// Tokens that would have been accepted at this position, sorted and deduplicated.
+    pub expected: Vec<&'static str>,
+    /// The source line the error points at.
+    pub snippet: String,
+}
+
+impl ParseError {
+    pub fn new(source: &str, error: Error<&str>) -> Self {
+        Self::at(source, source.offset(error.input), error.expected)
+    }
+
+    pub fn at(source: &str, offset: usize, mut expected: Vec<&'static str>) -> Self {
+        expected.sort_unstable();
+        expected.dedup();
+
+        let line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[offset..].find('\n').map_or(source.len(), |i| offset + i);
+
+        Self {
+            offset,
+            line: source[..offset].matches('\n').count() + 1,
+            column: source[line_start..offset].chars().count() + 1,
+            expected,
+            snippet: source[line_start..line_end].trim_end_matches('\r').to_owned(),
+        }
+    }
+
+    /// The text at the error, from the first non-blank character after the column to the next blank.
+    fn found(&self) -> String {
+        let token: String = self
+            .snippet
+            .chars()
+            .skip(self.column - 1)
+            .skip_while(|c| c.is_whitespace())
+            .take_while(|c| !c.is_whitespace())
+            .collect();
+
+        if token.is_empty() {
+            "end of line".to_owned()
+        } else {
+            format!("'{}'", token)
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
+        write!(format, "line {}, column {}: ", self.line, self.column)?;
+        match self.expected.len() {
+            0 => write!(format, "unexpected {}", self.found())?,
+            1 => write!(format, "expected {}, found {}", self.expected[0], self.found())?,
+            _ => write!(format, "expected one of {}, found {}", self.expected.join(", "), self.found())?,
+        }
+        writeln!(format)?;
+        writeln!(format, "    {}", self.snippet)?;
+        write!(format, "    {:>width$}", "^", width = self.column)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::ParseError;
+
+    fn parse_error(source: &str) -> ParseError {
+        crate::parse_ast(source).expect_err("the source has a syntax error")
+    }
+
+    #[test]
+    fn points_at_the_unexpected_token() {
+        let err = parse_error("let x = 1 2;");
+
+        assert_eq!((err.offset, err.line, err.column), (10, 1, 11));
+        assert_eq!(err.expected, vec!["`;`"]);
+        assert_eq!(err.snippet, "let x = 1 2;");
+        assert_eq!(
+            err.to_string(),
+            "line 1, column 11: expected `;`, found '2;'\n    let x = 1 2;\n              ^"
+        );
+    }
+
+    #[test]
+    fn counts_columns_in_characters() {
+        let err = parse_error("let s = \"ñandú\" 2;");
+
+        // `ñ` and `ú` take two bytes each but are one column.
+        assert_eq!((err.offset, err.line, err.column), (18, 1, 17));
+        assert_eq!(err.snippet, "let s = \"ñandú\" 2;");
+    }
+
+    #[test]
+    fn reports_the_line_of_the_error() {
+        let err = parse_error("let a = \"é\";\nlet b = (1 + ;\nlet c = 3;");
+
+        assert_eq!((err.line, err.column), (2, 14));
+        assert_eq!(err.expected, vec!["expression"]);
+        assert_eq!(err.snippet, "let b = (1 + ;");
+    }
+
+    #[test]
+    fn skips_blanks_to_find_the_token() {
+        let err = parse_error("fn f( { }");
+
+        assert_eq!(err.expected, vec!["`)`"]);
+        assert!(err.to_string().starts_with("line 1, column 6: expected `)`, found '{'"));
+    }
+
+    #[test]
+    fn reports_the_end_of_the_line() {
+        let err = parse_error("let x =\n");
+
+        assert_eq!((err.line, err.column), (2, 1));
+        assert!(err.to_string().contains("expected expression, found end of line"));
+    }
+
+    #[test]
+    fn rejects_deeply_nested_expressions() {
+        let nested = |depth| format!("let x = {}1{};", "(".repeat(depth), ")".repeat(depth));
+
+        assert!(crate::parse_ast(&nested(90)).is_ok());
+
+        let err = parse_error(&nested(10_000));
+        assert_eq!(err.expected, vec!["shallower nesting"]);
+    }
+
+    #[test]
+    fn rejects_deeply_nested_statements() {
+        for (open, close) in [("{", "}"), ("if (x) {", "}"), ("while (x) { let y = (((1)));", "}"), ("fn f() {", "}")] {
+            let nested = |depth| format!("{}{}", open.repeat(depth), close.repeat(depth));
+
+            assert!(crate::parse_ast(&nested(90)).is_ok());
+
+            let err = parse_error(&nested(5_000));
+            assert_eq!(err.expected, vec!["shallower nesting"]);
+        }
+
+        let chain = format!("if (x) {{}}{}", " else if (x) {}".repeat(5_000));
+        assert_eq!(parse_error(&chain).expected, vec!["shallower nesting"]);
+    }
+}
@@ -0,0 +1,444 @@
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::multispace0 as multispace,
+    combinator::{cut, map, opt, value},
+    error::context,
+    multi::{many0, separated_list0, separated_list1},
+    sequence::{delimited, pair, preceded, terminated, tuple},
+    Err, Parser,
+};
+
+use crate::ast::{
+    CallExpression, ConditionalExpression, Expression, Identifier, LogicalOper, MatchArm, MatchExpression, MemberExpression, NumericLiteral, Object,
+    Oper, Pattern, Property, UnaryOper, UpdateExpression,
+};
+use crate::error::{Error, IResult};
+
+use super::generic::{get_identifier, identifier, keyword, nested, string_literal};
+
+#[derive(Clone, Copy)]
+enum Infix {
+    Binary(Oper),
+    Logical(LogicalOper),
+}
+
+/// Binary operators with their binding power, a higher power binds tighter. Bitwise operators bind
+/// tighter than comparisons, so `flags & MASK == 0` tests the masked bits.
+const INFIX_OPERATORS: [(&str, Infix, u8); 24] = [
+    ("??", Infix::Logical(LogicalOper::Coalesce), 1),
+    ("||", Infix::Logical(LogicalOper::Or), 2),
+    ("&&", Infix::Logical(LogicalOper::And), 3),
+    ("==", Infix::Binary(Oper::Eq), 4),
+    ("!=", Infix::Binary(Oper::NotEq), 4),
+    ("===", Infix::Binary(Oper::StrictEq), 4),
+    ("!==", Infix::Binary(Oper::StrictNotEq), 4),
+    ("is", Infix::Binary(Oper::Is), 4),
+    ("<=", Infix::Binary(Oper::LtEq), 5),
+    (">=", Infix::Binary(Oper::GtEq), 5),
+    ("<", Infix::Binary(Oper::Lt), 5),
+    (">", Infix::Binary(Oper::Gt), 5),
+    ("|", Infix::Binary(Oper::BitOr), 6),
+    ("^", Infix::Binary(Oper::BitXor), 7),
+    ("&", Infix::Binary(Oper::BitAnd), 8),
+    ("<<", Infix::Binary(Oper::Shl), 9),
+    (">>", Infix::Binary(Oper::Shr), 9),
+    ("+", Infix::Binary(Oper::Add), 10),
+    ("-", Infix::Binary(Oper::Sub), 10),
+    ("*", Infix::Binary(Oper::Mul), 11),
+    ("//", Infix::Binary(Oper::FloorDiv), 11),
+    ("/", Infix::Binary(Oper::Div), 11),
+    ("%", Infix::Binary(Oper::Mod), 11),
+    ("**", Infix::Binary(Oper::Pow), POW_POWER),
+];
+
+/// Prefix operators bind tighter than every binary operator but `**`, `-a * b` is `(-a) * b`
+/// while `-a ** 2` is `-(a ** 2)`.
+const PREFIX_POWER: u8 = 12;
+
+/// `**` is right-associative, `2 ** 3 ** 2` is `2 ** (3 ** 2)`.
+const POW_POWER: u8 = 13;
+
+/// `c ? a : b` binds looser than every binary operator and is right-associative, so
+/// `a ? b : c ? d : e` is `a ? b : (c ? d : e)`.
+const TERNARY_POWER: u8 = 0;
+
+/// The longest operator `input` starts with, so `<=` is not read as `<`. A word operator must not
+/// run into an identifier, `a island` is not `a is land`.
+fn infix_operator(input: &str) -> Option<(&str, Infix, u8)> {
+    INFIX_OPERATORS
+        .iter()
+        .filter(|(token, _, _)| {
+            if token.starts_with(char::is_alphabetic) {
+                keyword(token)(input).is_ok()
+            } else {
+                input.starts_with(token)
+            }
+        })
+        .max_by_key(|(token, _, _)| token.len())
+        .map(|&(token, operator, power)| (&input[token.len()..], operator, power))
+}
+
+/// Comma separated items of a list, a trailing comma is allowed.
+fn list<'a, O>(item: impl Parser<&'a str, O, Error<&'a str>>) -> impl FnMut(&'a str) -> IResult<&'a str, Vec<O>> {
+    preceded(
+        multispace,
+        terminated(separated_list0(pair(tag(","), multispace), terminated(item, multispace)), opt(terminated(tag(","), multispace))),
+    )
+}
+
+/// A run of digits in `radix` where single `_` may separate digits, e.g. `1_000_000`. Returns the
+/// digits without the separators.
+fn digits<'a>(i: &'a str, radix: u32, label: &'static str) -> IResult<&'a str, String> {
+    let end = i.find(|c: char| !c.is_digit(radix) && c != '_').unwrap_or(i.len());
+    let run = &i[..end];
+
+    if run.is_empty() || run.starts_with('_') {
+        return Err(Err::Failure(Error::expected(i, label)));
+    }
+    if let Some(n) = run.find("__").or(run.ends_with('_').then(|| run.len() - 1)) {
+        return Err(Err::Failure(Error::expected(&i[n + 1..], label)));
+    }
+
+    Ok((&i[end..], run.replace('_', "")))
+}
+
+/// Decimal (`42`, `3.14`, `1e-9`), hexadecimal (`0xFF`) and binary (`0b1010`) literals. Literals
+/// with a fraction or an exponent are floats, every other literal is an int. Hexadecimal and binary
+/// literals are bit patterns, so `0xFFFFFFFFFFFFFFFF` is `-1`. A literal that does not fit in 64
+/// bits or runs into letters is an error.
+fn number(i: &str) -> IResult<&str, Expression> {
+    if !i.starts_with(|c: char| c.is_ascii_digit()) {
+        return Err(Err::Error(Error::expected(i, "number")));
+    }
+
+    let radix = match i.get(..2) {
+        Some("0x" | "0X") => Some((16, "hex digit")),
+        Some("0b" | "0B") => Some((2, "binary digit")),
+        _ => None,
+    };
+
+    let (rest, value, label) = match radix {
+        Some((radix, label)) => {
+            let (rest, digits) = digits(&i[2..], radix, label)?;
+            let value = match u64::from_str_radix(&digits, radix) {
+                Ok(value) => NumericLiteral::Int(value as i64),
+                Err(_) => return Err(Err::Failure(Error::expected(i, "number within range"))),
+            };
+            (rest, value, label)
+        }
+        None => {
+            let (mut rest, mut literal) = digits(i, 10, "digit")?;
+            let mut float = false;
+
+            if let Some(fraction) = rest.strip_prefix('.').filter(|f| f.starts_with(|c: char| c.is_ascii_digit())) {
+                let (fraction_rest, fraction) = digits(fraction, 10, "digit")?;
+                literal = format!("{}.{}", literal, fraction);
+                rest = fraction_rest;
+                float = true;
+            }
+
+            if let Some(exponent) = rest.strip_prefix(['e', 'E']) {
+                let (exponent, sign) = match exponent.strip_prefix(['+', '-']) {
+                    Some(unsigned) => (unsigned, &exponent[..1]),
+                    None => (exponent, ""),
+                };
+                let (exponent_rest, exponent) = digits(exponent, 10, "exponent digits")?;
+                literal = format!("{}e{}{}", literal, sign, exponent);
+                rest = exponent_rest;
+                float = true;
+            }
+
+            let value = if float {
+                literal.parse::<f64>().ok().filter(|x| x.is_finite()).map(NumericLiteral::Float)
+            } else {
+                literal.parse::<i64>().ok().map(NumericLiteral::Int)
+            };
+            match value {
+                Some(value) => (rest, value, "digit"),
+                None => return Err(Err::Failure(Error::expected(i, "number within range"))),
+            }
+        }
+    };
+
+    // `12abc` or `0xFG` is a malformed literal rather than a number followed by an identifier.
+    if rest.starts_with(|c: char| c.is_alphanumeric() || c == '_') {
+        return Err(Err::Failure(Error::expected(rest, label)));
+    }
+
+    Ok((rest, Expression::NumericLiteral(value)))
+}
+
+fn boolean(i: &str) -> IResult<&str, Expression> {
+    map(alt((value(true, keyword("true")), value(false, keyword("false")))), Expression::BooleanLiteral)(i)
+}
+
+fn null(i: &str) -> IResult<&str, Expression> {
+    value(Expression::NullLiteral, keyword("null"))(i)
+}
+
+fn parens(i: &str) -> IResult<&str, Expression> {
+    preceded(
+        tag("("),
+        cut(map(terminated(delimited(multispace, parse_expression, multispace), context("`)`", tag(")"))), |e| {
+            Expression::Paren(Box::new(e))
+        })),
+    )(i)
+}
+
+fn array(i: &str) -> IResult<&str, Expression> {
+    preceded(tag("["), cut(map(terminated(list(parse_expression), context("`]`", tag("]"))), Expression::ArrayLiteral)))(i)
+}
+
+fn property(i: &str) -> IResult<&str, Property> {
+    map(
+        pair(
+            alt((map(get_identifier, str::to_owned), string_literal)),
+            opt(preceded(tuple((multispace, tag(":"))), cut(parse_expression))),
+        ),
+        |(key, value)| Property { key, value },
+    )(i)
+}
+
+fn object(i: &str) -> IResult<&str, Expression> {
+    preceded(
+        tag("{"),
+        cut(map(terminated(list(context("property", property)), context("`}`", tag("}"))), |properties| {
+            Expression::ObjectLiteral(Object { properties })
+        })),
+    )(i)
+}
+
+/// A literal pattern, `_` or a name binding the matched value.
+fn pattern(i: &str) -> IResult<&str, Pattern> {
+    let negative = map(preceded(pair(tag("-"), multispace), number), |literal| match literal {
+        Expression::NumericLiteral(NumericLiteral::Int(x)) => Expression::NumericLiteral(NumericLiteral::Int(-x)),
+        Expression::NumericLiteral(NumericLiteral::Float(x)) => Expression::NumericLiteral(NumericLiteral::Float(-x)),
+        literal => literal,
+    });
+
+    context(
+        "pattern",
+        alt((
+            value(Pattern::Wildcard, keyword("_")),
+            map(alt((number, negative, map(string_literal, Expression::StringLiteral), boolean, null)), Pattern::Literal),
+            map(identifier, |name: &str| Pattern::Binding(name.to_owned())),
+        )),
+    )(i)
+}
+
+fn match_arm(i: &str) -> IResult<&str, MatchArm> {
+    map(
+        pair(
+            separated_list1(delimited(multispace, tag("|"), multispace), pattern),
+            cut(preceded(pair(multispace, context("`=>`", tag("=>"))), parse_expression)),
+        ),
+        |(patterns, body)| MatchArm { patterns, body },
+    )(i)
+}
+
+fn match_expression(i: &str) -> IResult<&str, Expression> {
+    let (i, (_, (value, _, _, arms, _))) = tuple((
+        keyword("match"),
+        cut(tuple((parse_expression, multispace, context("`{`", tag("{")), list(match_arm), context("`}`", tag("}"))))),
+    ))(i)?;
+
+    Ok((i, Expression::Match(MatchExpression { value: Box::new(value), arms })))
+}
+
+fn primary(i: &str) -> IResult<&str, Expression> {
+    let result = alt((
+        number,
+        map(string_literal, Expression::StringLiteral),
+        boolean,
+        null,
+        array,
+        object,
+        parens,
+        match_expression,
+        map(identifier, |id: &str| Expression::Identifier(Identifier { id: id.to_owned() })),
+    ))(i);
+
+    // None of the alternatives matched, which is reported as a missing expression rather than
+    // listing every kind of literal.
+    match result {
+        Err(Err::Error(_)) => Err(Err::Error(Error::expected(i, "expression"))),
+        result => result,
+    }
+}
+
+enum Postfix {
+    Call(Vec<Expression>),
+    Member(Expression, bool),
+}
+
+fn postfix_operator(i: &str) -> IResult<&str, Postfix> {
+    preceded(
+        multispace,
+        alt((
+            map(preceded(tag("("), cut(terminated(list(parse_expression), context("`)`", tag(")"))))), Postfix::Call),
+            map(
+                preceded(tag("["), cut(terminated(delimited(multispace, parse_expression, multispace), context("`]`", tag("]"))))),
+                |index| Postfix::Member(index, true),
+            ),
+            map(preceded(pair(tag("."), multispace), cut(get_identifier)), |name: &str| {
+                Postfix::Member(Expression::Identifier(Identifier { id: name.to_owned() }), false)
+            }),
+        )),
+    )(i)
+}
+
+/// A primary expression followed by any number of calls and member accesses, e.g. `a.b["c"](1).d`.
+pub fn postfix(i: &str) -> IResult<&str, Expression> {
+    let (i, initial) = primary(i)?;
+    let (i, operators) = many0(postfix_operator)(i)?;
+
+    Ok((
+        i,
+        operators.into_iter().fold(initial, |acc, operator| match operator {
+            Postfix::Call(args) => Expression::Call(CallExpression {
+                callee: Box::new(acc),
+                args,
+            }),
+            Postfix::Member(property, computed) => Expression::Member(MemberExpression {
+                object: Box::new(acc),
+                property: Box::new(property),
+                computed,
+            }),
+        }),
+    ))
+}
+
+/// Whether `expr` can be assigned to, a variable or a member expression.
+pub fn is_assignable(expr: &Expression) -> bool {
+    match expr {
+        Expression::Identifier(_) | Expression::Member(_) => true,
+        Expression::Paren(inner) => is_assignable(inner),
+        _ => false,
+    }
+}
+
+fn update_operator(i: &str) -> IResult<&str, Oper> {
+    alt((value(Oper::Add, tag("++")), value(Oper::Sub, tag("--"))))(i)
+}
+
+/// A postfix expression, optionally followed by `++` or `--`.
+fn postfix_update(i: &str) -> IResult<&str, Expression> {
+    let (rest, target) = postfix(i)?;
+    let (rest, operator) = match update_operator(rest) {
+        Ok(result) => result,
+        Err(_) => return Ok((rest, target)),
+    };
+
+    if !is_assignable(&target) {
+        return Err(Err::Failure(Error::expected(i, "assignment target")));
+    }
+    Ok((
+        rest,
+        Expression::Update(UpdateExpression {
+            target: Box::new(target),
+            operator,
+            prefix: false,
+        }),
+    ))
+}
+
+/// `++target` or `--target`.
+fn prefix_update(i: &str) -> IResult<&str, Expression> {
+    let (i, operator) = update_operator(i)?;
+    let (rest, target) = cut(preceded(multispace, postfix))(i)?;
+
+    if !is_assignable(&target) {
+        return Err(Err::Failure(Error::expected(multispace(i)?.0, "assignment target")));
+    }
+    Ok((
+        rest,
+        Expression::Update(UpdateExpression {
+            target: Box::new(target),
+            operator,
+            prefix: true,
+        }),
+    ))
+}
+
+fn prefix_operator(i: &str) -> IResult<&str, UnaryOper> {
+    alt((
+        value(UnaryOper::Neg, tag("-")),
+        value(UnaryOper::Plus, tag("+")),
+        value(UnaryOper::Not, tag("!")),
+        value(UnaryOper::BitNot, tag("~")),
+    ))(i)
+}
+
+fn prefix(i: &str) -> IResult<&str, Expression> {
+    let (i, _) = multispace(i)?;
+
+    alt((
+        prefix_update,
+        map(pair(prefix_operator, cut(|i| expression(i, PREFIX_POWER))), |(operator, operand)| {
+            match (operator, operand) {
+                // A sign directly in front of a number is part of the literal.
+                // `-0x8000000000000000` has no int, negating it is left to the runtime to report.
+                (UnaryOper::Neg, Expression::NumericLiteral(NumericLiteral::Int(value))) if value != i64::MIN => {
+                    Expression::NumericLiteral(NumericLiteral::Int(-value))
+                }
+                (UnaryOper::Neg, Expression::NumericLiteral(NumericLiteral::Float(value))) => {
+                    Expression::NumericLiteral(NumericLiteral::Float(-value))
+                }
+                (UnaryOper::Plus, literal @ Expression::NumericLiteral(_)) => literal,
+                (operator, operand) => Expression::Unary(operator, Box::new(operand)),
+            }
+        }),
+        postfix_update,
+    ))(i)
+}
+
+fn expression(i: &str, min_power: u8) -> IResult<&str, Expression> {
+    nested(i, |i| climb(i, min_power))
+}
+
+/// Precedence climbing: parses operands joined by operators binding at least as tight as
+/// `min_power`. The right side of an operator only takes tighter operators, which makes every
+/// binary operator but `**` left-associative.
+fn climb(i: &str, min_power: u8) -> IResult<&str, Expression> {
+    let (mut i, mut left) = prefix(i)?;
+
+    loop {
+        let (rest, _) = multispace(i)?;
+
+        if min_power == TERNARY_POWER && rest.starts_with('?') && !rest.starts_with("??") {
+            let (rest, (consequent, _, _, alternate)) = cut(tuple((
+                preceded(tag("?"), parse_expression),
+                multispace,
+                context("`:`", tag(":")),
+                |i| expression(i, TERNARY_POWER),
+            )))(rest)?;
+            left = Expression::Conditional(ConditionalExpression {
+                condition: Box::new(left),
+                consequent: Box::new(consequent),
+                alternate: Box::new(alternate),
+            });
+            i = rest;
+            continue;
+        }
+
+        let (rest, operator, power) = match infix_operator(rest) {
+            Some((rest, operator, power)) if power >= min_power => (rest, operator, power),
+            _ => break,
+        };
+
+        let right_power = if power == POW_POWER { power } else { power + 1 };
+        let (rest, right) = cut(|i| expression(i, right_power))(rest)?;
+        left = match operator {
+            Infix::Binary(operator) => Expression::Binary(Box::new(left), operator, Box::new(right)),
+            Infix::Logical(operator) => Expression::Logical(Box::new(left), operator, Box::new(right)),
+        };
+        i = rest;
+    }
+
+    Ok((i, left))
+}
+
+pub fn parse_expression(input: &str) -> IResult<&str, Expression> {
+    expression(input, 0)
+}
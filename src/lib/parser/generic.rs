@@ -1,17 +1,63 @@
+use std::cell::Cell;
+
 use nom::{
     branch::alt,
     bytes::complete::tag,
-    character::complete::{alpha1, alphanumeric1},
-    combinator::recognize,
+    character::complete::{alpha1, alphanumeric1, satisfy},
+    combinator::{not, recognize},
+    error::{context, ErrorKind, ParseError},
     multi::many0_count,
-    sequence::pair,
-    IResult,
+    sequence::{pair, terminated},
     Err,
-    error::{Error, ErrorKind, ParseError},
 };
 
+use crate::error::{Error, IResult};
+
 pub fn get_identifier(input: &str) -> IResult<&str, &str> {
-    recognize(pair(alt((alpha1, tag("_"))), many0_count(alt((alphanumeric1, tag("_"))))))(input)
+    context("identifier", recognize(pair(alt((alpha1, tag("_"))), many0_count(alt((alphanumeric1, tag("_")))))))(input)
+}
+
+/// Words that cannot be used as variable, function or parameter names.
+const RESERVED: [&str; 16] = [
+    "let", "const", "fn", "return", "if", "else", "while", "for", "in", "break", "continue", "match", "is", "true", "false", "null",
+];
+
+/// An identifier that is not a reserved word. Property names after a `.` or in an object literal
+/// use `get_identifier` instead, so `obj.if` stays valid.
+pub fn identifier(input: &str) -> IResult<&str, &str> {
+    let (rest, id) = get_identifier(input)?;
+    if RESERVED.contains(&id) {
+        return Err(Err::Error(Error::expected(input, "identifier")));
+    }
+
+    Ok((rest, id))
+}
+
+/// Matches `word` only when it is not the prefix of a longer identifier, e.g. `fn` but not `fnord`.
+pub fn keyword(word: &'static str) -> impl Fn(&str) -> IResult<&str, &str> {
+    move |input: &str| terminated(tag(word), not(satisfy(|c: char| c.is_alphanumeric() || c == '_')))(input)
+}
+
+/// How deep statements and expressions can nest together, e.g. `{ { {} } }` or `((1))`, before
+/// parsing fails instead of running out of stack.
+const MAX_NESTING: usize = 100;
+
+thread_local! {
+    static NESTING: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Runs `parser` one nesting level deeper than the enclosing statement or expression, failing past
+/// `MAX_NESTING`.
+pub fn nested<'a, O>(input: &'a str, parser: impl FnOnce(&'a str) -> IResult<&'a str, O>) -> IResult<&'a str, O> {
+    let depth = NESTING.get();
+    if depth >= MAX_NESTING {
+        return Err(Err::Failure(Error::expected(input, "shallower nesting")));
+    }
+
+    NESTING.set(depth + 1);
+    let result = parser(input);
+    NESTING.set(depth);
+    result
 }
 
 // fn take_until_closing_bracket(opening_bracket: &str, closing_bracket: &str, tokens: &mut Vec<Token>) -> Vec<Token> {
@@ -38,21 +84,102 @@ pub fn get_identifier(input: &str) -> IResult<&str, &str> {
 //     return tokens[1..close_pos - 1].to_vec();
 // }
 
+/// Returns the index right after the string literal opening at `start`, or `None` when the literal
+/// is not terminated.
+fn skip_string(input: &str, start: usize) -> Option<usize> {
+    let mut chars = input[start..].char_indices();
+    let quote = chars.next().map(|(_, c)| c).unwrap_or_default();
+
+    while let Some((n, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            c if c == quote => return Some(start + n + c.len_utf8()),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+fn parse_unicode_escape(input: &str) -> Option<(char, usize)> {
+    let digits = input.strip_prefix('{')?;
+    let end = digits.find('}')?;
+    let code = u32::from_str_radix(&digits[..end], 16).ok()?;
+
+    Some((char::from_u32(code)?, end + 2))
+}
+
+/// Parses a single or double quoted string literal, returning its value with escapes resolved.
+pub fn string_literal(input: &str) -> IResult<&str, String> {
+    let quote = match input.chars().next() {
+        Some(c @ ('"' | '\'')) => c,
+        _ => return Err(Err::Error(Error::expected(input, "string"))),
+    };
+
+    let mut value = String::new();
+    let mut chars = input.char_indices().skip(1);
+    while let Some((index, c)) = chars.next() {
+        match c {
+            c if c == quote => return Ok((&input[index + 1..], value)),
+            '\\' => {
+                let (escape_index, escape) = chars.next().unwrap_or((input.len(), '\0'));
+                let escaped = match escape {
+                    'n' => '\n',
+                    't' => '\t',
+                    'r' => '\r',
+                    '0' => '\0',
+                    '\\' | '"' | '\'' => escape,
+                    'u' => match parse_unicode_escape(&input[escape_index + 1..]) {
+                        Some((c, len)) => {
+                            // Skip the `{...}` part of the escape.
+                            for _ in input[escape_index + 1..escape_index + 1 + len].chars() {
+                                chars.next();
+                            }
+                            c
+                        }
+                        None => return Err(Err::Failure(Error::expected(&input[escape_index..], "unicode escape `\\u{...}`"))),
+                    },
+                    _ => return Err(Err::Failure(Error::expected(&input[index..], "escape sequence"))),
+                };
+                value.push(escaped);
+            }
+            c => value.push(c),
+        }
+    }
+
+    let closing = if quote == '"' { "`\"`" } else { "`'`" };
+    Err(Err::Failure(Error::expected(&input[input.len()..], closing)))
+}
+
 pub fn take_until_unbalanced(opening_bracket: char, closing_bracket: char) -> impl Fn(&str) -> IResult<&str, &str> {
     move |i: &str| {
         let mut index = 0;
         let mut bracket_counter = 0;
-        while let Some(n) = &i[index..].find(&[opening_bracket, closing_bracket, '\\'][..]) {
+        while let Some(n) = &i[index..].find(&[opening_bracket, closing_bracket, '\\', '"', '\'', '/'][..]) {
             index += n;
             let mut it = i[index..].chars();
             match it.next().unwrap_or_default() {
-                c if c == '\\' => {
+                '\\' => {
                     // Skip the escape char `\`.
                     index += '\\'.len_utf8();
-                    // Skip also the following char.
-                    let c = it.next().unwrap_or_default();
-                    index += c.len_utf8();
+                    // Skip also the following char, unless the input ends with the `\`.
+                    match it.next() {
+                        Some(c) => index += c.len_utf8(),
+                        None => break,
+                    }
+                }
+                '"' | '\'' => match skip_string(i, index) {
+                    Some(end) => index = end,
+                    None => break,
+                },
+                // Comments only start a statement, elsewhere `//` is the floor division operator.
+                '/' if i[index..].starts_with("//") && (i[..index].trim_end().is_empty() || i[..index].trim_end().ends_with([';', '{', '}'])) => {
+                    // Skip the comment, it may contain brackets or quotes.
+                    index = i[index..].find('\n').map_or(i.len(), |n| index + n);
                 }
+                '/' => index += '/'.len_utf8(),
                 c if c == opening_bracket => {
                     bracket_counter += 1;
                     index += opening_bracket.len_utf8();
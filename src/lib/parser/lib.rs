@@ -1,304 +1,329 @@
-mod arithmetic;
 pub mod ast;
+mod error;
+mod expression;
 mod generic;
 
-use generic::get_identifier;
+use generic::{identifier, keyword, nested};
 use nom::{
     branch::alt,
-    bytes::complete::{tag, take_till, take_until},
-    character::complete::{digit1, multispace0, space0},
-    combinator::opt,
-    multi::{many0, separated_list1},
-    sequence::{delimited, tuple},
-    IResult,
+    bytes::complete::{tag, take_till},
+    character::complete::{multispace0, multispace1, space0},
+    combinator::{cut, map, not, opt, value},
+    error::context,
+    multi::separated_list0,
+    sequence::{delimited, preceded, terminated, tuple},
+    Err,
+};
+use std::{
+    fmt::{self, Debug, Display, Formatter},
+    rc::Rc,
 };
-use std::fmt::{self, Debug, Display, Formatter};
 
-use arithmetic::parse_arithmetic_expression;
-use ast::{Assign, CallExpression, Identifier, MemberExpression, NumericLiteral, Object, Property, Statement};
+use ast::{
+    Assign, CompoundAssign, Expression, ForInStatement, ForStatement, FunctionDeclaration, IfStatement, MemberAssign, Oper, Statement, WhileStatement,
+};
+use error::{Error, IResult};
+pub use error::ParseError;
+use expression::{is_assignable, parse_expression, postfix};
 // use lexer::{Token, TokenType};
 
 #[derive(Debug)]
-pub struct Program<'a> {
-    pub body: Vec<Statement<'a>>,
+pub struct Program {
+    pub body: Vec<Statement>,
 }
 
-impl Display for Program<'_> {
+impl Display for Program {
     fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
-        write!(format, "Program:\n")?;
+        writeln!(format, "Program:")?;
         for v in &self.body {
-            write!(format, "\t{}\n", v)?;
+            writeln!(format, "\t{}", v)?;
         }
         Ok(())
     }
 }
 
+fn semicolon(input: &str) -> IResult<&str, &str> {
+    preceded(multispace0, context("`;`", tag(";")))(input)
+}
+
 fn parse_comment(input: &str) -> IResult<&str, Statement> {
     let (input, (_, _, comment)) = tuple((space0, tag("//"), take_till(|c| c == '\n' || c == '\r')))(input)?;
 
     Ok((input, Statement::Comment(comment.trim().to_owned())))
 }
 
-fn parse_call_member_expression(input: &str) -> IResult<&str, Statement> {
-    let (input, (object, property)) = tuple((
-        generic::get_identifier,
-        delimited(tag("["), delimited(tag("\""), get_identifier, tag("\"")), tag("]")),
+/// `let x = 1;` or `const x = 1;`, a `let` without a value, `let x;`, holds `null`.
+fn parse_declaration(input: &str) -> IResult<&str, Statement> {
+    let (input, (kind, (_, id, _))) = tuple((
+        context("declaration", alt((keyword("let"), keyword("const")))),
+        cut(tuple((multispace1, identifier, multispace0))),
     ))(input)?;
 
-    Ok((input, Statement::MemberExpression(MemberExpression { object, property })))
-}
-
-fn parse_call_member_expression_literal(input: &str) -> IResult<&str, Statement> {
-    let (input, (_, stat, _, _, _)) = tuple((multispace0, parse_call_member_expression, multispace0, tag(";"), multispace0))(input)?;
-
-    Ok((input, stat))
-}
-
-// fn parse_member_expression(input: &str) -> IResult<&str, Statement> {
-//     let (_, members) = separated_list1(tag("."), get_identifier)(input)?;
-
-//     Ok((input, Statement::MemberExpression(MemberExpression { object: "", property: "" })))
-// }
-
-// fn parse_member_expression_literal(input: &str) -> IResult<&str, Statement> {
-//     let (input, (_, stat, _, _, _)) = tuple((multispace0, parse_member_expression, multispace0, tag(";"), multispace0))(input)?;
-
-//     Ok((input, stat))
-// }
-
-fn parse_identifier(input: &str) -> IResult<&str, Statement> {
-    let (input, (_, id, _, _, _)) = tuple((multispace0, generic::get_identifier, multispace0, tag(";"), multispace0))(input)?;
+    let constant = kind == "const";
+    let initializer = delimited(context("`=`", tag("=")), parse_expression, semicolon);
+    let (input, expression) = if constant {
+        cut(initializer)(input)?
+    } else {
+        cut(alt((value(Expression::NullLiteral, context("`;`", tag(";"))), initializer)))(input)?
+    };
 
-    Ok((input, Statement::Identifier(Identifier { id: id.to_owned() })))
+    Ok((
+        input,
+        Statement::Declaration(Assign {
+            id: id.to_owned(),
+            constant,
+            expression,
+        }),
+    ))
 }
 
-fn parse_boolean(input: &str) -> IResult<&str, Statement> {
-    let (input, boolean) = alt((tag("true"), tag("false")))(input)?;
-
-    Ok((input, Statement::BooleanLiteral(boolean.parse::<bool>().unwrap())))
+fn compound_operator(input: &str) -> IResult<&str, Oper> {
+    alt((
+        value(Oper::Add, tag("+=")),
+        value(Oper::Sub, tag("-=")),
+        value(Oper::Mul, tag("*=")),
+        value(Oper::Div, tag("/=")),
+        value(Oper::Mod, tag("%=")),
+    ))(input)
 }
 
-fn parse_boolean_literal(input: &str) -> IResult<&str, Statement> {
-    let (input, (_, boolean, _, _, _)) = tuple((multispace0, alt((tag("true"), tag("false"))), multispace0, tag(";"), multispace0))(input)?;
+/// `target op= expression` without the trailing `;`.
+fn parse_compound_assignment(input: &str) -> IResult<&str, Statement> {
+    let (rest, (target, _, operator)) = tuple((postfix, multispace0, compound_operator))(input)?;
+    if !is_assignable(&target) {
+        return Err(Err::Failure(Error::expected(input, "assignment target")));
+    }
+    let (rest, expression) = cut(parse_expression)(rest)?;
 
-    Ok((input, Statement::BooleanLiteral(boolean.parse::<bool>().unwrap())))
+    Ok((rest, Statement::CompoundAssign(CompoundAssign { target, operator, expression })))
 }
 
-fn get_property_assignment(input: &str) -> IResult<&str, Statement> {
-    let (input, (_, _, statement, _)) = tuple((
-        tag(":"),
-        multispace0,
-        alt((
-            parse_boolean,
-            parse_numeric,
-            parse_identifier,
-            parse_arithmetic_expression_to_expr,
-            parse_object,
+fn parse_simple_assignment(input: &str) -> IResult<&str, Statement> {
+    let (rest, (target, _, _, _)) = tuple((postfix, multispace0, tag("="), not(tag("="))))(input)?;
+    let (rest, expression) = cut(parse_expression)(rest)?;
+
+    match target {
+        Expression::Identifier(id) => Ok((
+            rest,
+            Statement::Assign(Assign {
+                id: id.id,
+                constant: false,
+                expression,
+            }),
         )),
-        multispace0,
-    ))(input)?;
-
-    Ok((input, statement))
+        Expression::Member(target) => Ok((rest, Statement::MemberAssign(MemberAssign { target, expression }))),
+        _ => Err(Err::Failure(Error::expected(input, "assignment target"))),
+    }
 }
 
-fn parse_object_property(input: &str) -> IResult<&str, Property> {
-    let (input, (_, key, _, statement, _, _, _)) = tuple((
-        multispace0,
-        get_identifier,
-        multispace0,
-        opt(get_property_assignment),
-        multispace0,
-        opt(tag(",")),
-        multispace0,
-    ))(input)?;
-
-    if let Some(stat) = statement {
-        return Ok((
-            input,
-            Property {
-                key,
-                value: Some(Box::new(stat)),
-            },
-        ));
-    }
+/// `target = expression` or `target op= expression` without the trailing `;`, the target is a
+/// variable or a member expression.
+fn parse_assignment(input: &str) -> IResult<&str, Statement> {
+    alt((parse_compound_assignment, parse_simple_assignment))(input)
+}
 
-    return Ok((input, Property { key, value: None }));
+fn parse_assign(input: &str) -> IResult<&str, Statement> {
+    terminated(parse_assignment, cut(semicolon))(input)
 }
 
-fn parse_object_properties(input: &str) -> IResult<&str, Vec<Property>> {
-    let (input, (_, properties, _)) = tuple((multispace0, many0(parse_object_property), multispace0))(input)?;
+fn parse_expression_statement(input: &str) -> IResult<&str, Statement> {
+    map(terminated(parse_expression, semicolon), Statement::Expression)(input)
+}
 
-    Ok((input, properties))
+/// The `( expression )` following `if` and `while`.
+fn parse_condition(input: &str) -> IResult<&str, Expression> {
+    delimited(
+        tuple((multispace0, context("`(`", tag("(")))),
+        parse_expression,
+        tuple((multispace0, context("`)`", tag(")")))),
+    )(input)
 }
 
-fn parse_object(input: &str) -> IResult<&str, Statement> {
-    let (input, obj) = delimited(tag("{"), generic::take_until_unbalanced('{', '}'), tag("}"))(input)?;
+fn parse_block(input: &str) -> IResult<&str, Vec<Statement>> {
+    let (mut input, _) = tuple((multispace0, context("`{`", tag("{")), multispace0))(input)?;
 
-    let (rem, properties) = parse_object_properties(obj)?;
+    let mut statements = Vec::new();
+    loop {
+        if let Ok((rest, _)) = tag::<_, _, Error<&str>>("}")(input) {
+            return Ok((rest, statements));
+        }
+        if input.is_empty() {
+            return Err(Err::Failure(Error::expected(input, "`}`")));
+        }
 
-    if rem.len() > 0 {
-        panic!("Object remainder to be parsed: {}", rem);
+        let (rest, statement) = cut(|i| nested(i, parse_statement))(input)?;
+        statements.push(statement);
+        (input, _) = multispace0(rest)?;
     }
-
-    Ok((input, Statement::ObjectLiteral(Object { properties })))
 }
 
-fn parse_numeric(input: &str) -> IResult<&str, Statement> {
-    let (input, num) = digit1(input)?;
+fn parse_function_declaration(input: &str) -> IResult<&str, Statement> {
+    let (input, (_, (_, name, _, _, params, _, body))) = tuple((
+        keyword("fn"),
+        cut(tuple((
+            multispace1,
+            identifier,
+            multispace0,
+            context("`(`", tag("(")),
+            separated_list0(tag(","), delimited(multispace0, identifier, multispace0)),
+            context("`)`", tag(")")),
+            parse_block,
+        ))),
+    ))(input)?;
 
     Ok((
         input,
-        Statement::NumericLiteral(NumericLiteral {
-            value: num.parse::<f64>().unwrap(),
+        Statement::FunctionDeclaration(FunctionDeclaration {
+            name: name.to_owned(),
+            params: params.into_iter().map(str::to_owned).collect(),
+            body: Rc::new(body),
         }),
     ))
 }
 
-fn parse_numeric_literal(input: &str) -> IResult<&str, Statement> {
-    let (input, (_, num, _, _, _)) = tuple((multispace0, digit1, multispace0, tag(";"), multispace0))(input)?;
+fn parse_if(input: &str) -> IResult<&str, Statement> {
+    let (input, (_, (condition, consequent))) = tuple((keyword("if"), cut(tuple((parse_condition, parse_block)))))(input)?;
+
+    let (input, alternate) = opt(preceded(
+        tuple((multispace0, keyword("else"), multispace0)),
+        cut(alt((map(|i| nested(i, parse_if), |statement| vec![statement]), parse_block))),
+    ))(input)?;
 
     Ok((
         input,
-        Statement::NumericLiteral(NumericLiteral {
-            value: num.parse::<f64>().unwrap(),
+        Statement::If(IfStatement {
+            condition,
+            consequent,
+            alternate,
         }),
     ))
 }
 
-fn parse_declaration(input: &str) -> IResult<&str, Statement> {
-    let (input, (_, expression, _, id, _, _, _, assign, _, _, _)) = tuple((
-        multispace0,
-        alt((tag("let"), tag("const"))),
-        multispace0,
-        generic::get_identifier,
-        multispace0,
-        tag("="),
-        multispace0,
-        take_until(";"),
-        multispace0,
-        tag(";"),
-        multispace0,
-    ))(input)?;
-
-    let constant = match expression {
-        "let" => false,
-        "const" => true,
-        _ => panic!("unknown declaration expression '{}'", expression),
-    };
+fn parse_while(input: &str) -> IResult<&str, Statement> {
+    let (input, (_, (condition, body))) = tuple((keyword("while"), cut(tuple((parse_condition, parse_block)))))(input)?;
 
-    let (rem, expression) = alt((
-        parse_boolean,
-        parse_object,
-        parse_arithmetic_expression_to_expr,
-        // parse_member_expression,
-        parse_call_member_expression,
-    ))(assign)?;
+    Ok((input, Statement::While(WhileStatement { condition, body })))
+}
 
-    if rem.len() > 0 {
-        panic!("Input remainder to be parsed: {}", rem);
-    }
+/// The `x in` starting the header of a `for (x in items)`, once it matched the rest of the header
+/// must be the iterable.
+fn parse_for_in_binding(input: &str) -> IResult<&str, &str> {
+    let (input, (_, id, _, _)) = tuple((multispace0, identifier, multispace1, keyword("in")))(input)?;
 
-    Ok((
-        input,
-        Statement::Declaration(Assign {
-            id,
-            constant,
-            expression: Box::new(expression),
-        }),
-    ))
+    Ok((input, id))
 }
 
-fn parse_assign(input: &str) -> IResult<&str, Statement> {
-    let (input, (_, id, _, _, _, assign, _, _, _)) = tuple((
-        multispace0,
-        generic::get_identifier,
-        multispace0,
-        tag("="),
-        multispace0,
-        take_until(";"),
+type ForHeader = (Option<Statement>, Option<Expression>, Option<Statement>);
+
+/// The header of a C-style `for`, its update is an assignment without the trailing `;` or any expression.
+fn parse_for_header(input: &str) -> IResult<&str, ForHeader> {
+    let (input, (_, init)) = tuple((
         multispace0,
-        tag(";"),
+        alt((map(alt((parse_declaration, parse_assign)), Some), map(semicolon, |_| None))),
+    ))(input)?;
+
+    let (input, (condition, _, _, update)) = tuple((
+        opt(parse_expression),
+        semicolon,
         multispace0,
+        opt(alt((parse_assignment, map(parse_expression, Statement::Expression)))),
     ))(input)?;
 
-    let (rem, expression) = alt((
-        parse_boolean,
-        parse_object,
-        parse_arithmetic_expression_to_expr,
-        // parse_member_expression,
-        parse_call_member_expression,
-    ))(assign)?;
+    Ok((input, (init, condition, update)))
+}
+
+fn for_header_end(input: &str) -> IResult<&str, &str> {
+    preceded(multispace0, context("`)`", tag(")")))(input)
+}
 
-    if rem.len() > 0 {
-        panic!("Input remainder to be parsed: {}", rem);
+fn parse_for(input: &str) -> IResult<&str, Statement> {
+    let (input, _) = tuple((keyword("for"), multispace0, context("`(`", tag("("))))(input)?;
+
+    match parse_for_in_binding(input) {
+        Ok((input, id)) => {
+            let (input, (iterable, body)) = cut(tuple((terminated(parse_expression, for_header_end), parse_block)))(input)?;
+            return Ok((
+                input,
+                Statement::ForIn(ForInStatement {
+                    id: id.to_owned(),
+                    iterable,
+                    body,
+                }),
+            ));
+        }
+        Err(Err::Error(_)) => {}
+        Err(err) => return Err(err),
     }
 
+    let (input, ((init, condition, update), body)) = cut(tuple((terminated(parse_for_header, for_header_end), parse_block)))(input)?;
+
     Ok((
         input,
-        Statement::Assign(Assign {
-            id,
-            constant: false,
-            expression: Box::new(expression),
+        Statement::For(ForStatement {
+            init: init.map(Box::new),
+            condition,
+            update: update.map(Box::new),
+            body,
         }),
     ))
 }
 
-fn parse_call_expression(input: &str) -> IResult<&str, Statement> {
-    let (input, (_, func, _, _, _, args, _, _, _, _)) = tuple((
-        multispace0,
-        tag("print"),
-        multispace0,
-        tag("("),
-        multispace0,
-        take_until(")"),
-        tag(")"),
-        multispace0,
-        tag(";"),
-        multispace0,
-    ))(input)?;
+fn parse_loop_control(input: &str) -> IResult<&str, Statement> {
+    let (input, (control, _)) = tuple((alt((keyword("break"), keyword("continue"))), cut(semicolon)))(input)?;
 
-    Ok((
-        input,
-        Statement::CallExpression(CallExpression {
-            func,
-            args: args.trim().split(",").into_iter().map(|x| x.trim()).collect(),
-        }),
-    ))
+    match control {
+        "break" => Ok((input, Statement::Break)),
+        _ => Ok((input, Statement::Continue)),
+    }
 }
 
-fn parse_arithmetic_expression_to_expr(input: &str) -> IResult<&str, Statement> {
-    let (input, parsed) = parse_arithmetic_expression(input)?;
+fn parse_return(input: &str) -> IResult<&str, Statement> {
+    let (input, (_, value, _)) = tuple((keyword("return"), opt(parse_expression), cut(semicolon)))(input)?;
 
-    Ok((input, Statement::ArithmeticExpression(parsed)))
+    Ok((input, Statement::Return(value)))
 }
 
-fn parse_program(input: &str) -> IResult<&str, Vec<Statement>> {
-    let (input, program) = many0(alt((
+fn parse_statement(input: &str) -> IResult<&str, Statement> {
+    alt((
         parse_comment,
-        parse_boolean_literal,
-        parse_numeric_literal,
-        parse_identifier,
+        parse_function_declaration,
+        parse_return,
+        parse_if,
+        parse_while,
+        parse_for,
+        parse_loop_control,
+        // A `{` starting a statement opens a block rather than an object literal.
+        map(parse_block, Statement::Block),
         parse_declaration,
         parse_assign,
-        parse_call_expression,
-        // parse_member_expression_literal,
-        parse_call_member_expression_literal,
-        parse_arithmetic_expression_to_expr,
-    )))(input)?;
+        parse_expression_statement,
+    ))(input)
+}
 
-    let statements: Vec<Statement> = program;
+fn parse_program(input: &str) -> IResult<&str, Vec<Statement>> {
+    let mut statements = Vec::new();
+
+    let (mut input, _) = multispace0(input)?;
+    while !input.is_empty() {
+        let (rem, statement) = parse_statement(input)?;
+        statements.push(statement);
+        (input, _) = multispace0(rem)?;
+    }
 
     Ok((input, statements))
 }
 
-pub fn parse_ast(input: &str) -> IResult<&str, Program> {
-    let (input, statements) = parse_program(input)?;
-
-    let program = Program { body: statements };
+/// Returns `false` while `input` still has an unclosed `{`, `(` or `[`, meaning more input is needed before it can be parsed.
+pub fn is_balanced(input: &str) -> bool {
+    [('{', '}'), ('(', ')'), ('[', ']')]
+        .into_iter()
+        .all(|(open, close)| generic::take_until_unbalanced(open, close)(input).is_ok())
+}
 
-    if input.len() > 0 {
-        panic!("Input remainder to be parsed: {}", input);
+pub fn parse_ast(input: &str) -> Result<Program, ParseError> {
+    match parse_program(input) {
+        Ok((_, statements)) => Ok(Program { body: statements }),
+        Err(Err::Error(err)) | Err(Err::Failure(err)) => Err(ParseError::new(input, err)),
+        Err(Err::Incomplete(_)) => Err(ParseError::at(input, input.len(), vec!["statement"])),
     }
-
-    Ok((input, program))
 }
@@ -1,25 +1,142 @@
-use parser;
+use std::{
+    env, fs,
+    io::{self, Read},
+    process::ExitCode,
+    thread,
+};
 
+mod repl;
 mod runtime;
 
-fn main() {
-    let input = include_str!("sample.c420");
+const USAGE: &str = "Usage: codamus <command> [options] [file]
 
-    let program = parser::parse_ast(input);
-    
-    match program {
-        Ok((rem, program)) => {
-            print!("{}\n", program);
-            print!("remaining input: '{}'\n\n", rem);
+Commands:
+    run <file>      parse and evaluate a script, `--trace` prints every statement and its value
+    parse <file>    print the AST of a script
+    check <file>    parse a script and report errors
+    repl            start an interactive session
 
-            let _ = runtime::evaluate_program(program);
-            // println!("runtime value: {:?}", runtime_val);
+Use '-' or omit the file to read the script from stdin.";
 
-            // for expr in program.body {
-            //     let runtime_val = runtime::evaluate(expr);
-            //     println!("runtime value: {:?}", runtime_val);
-            // }
+enum Command {
+    Run { trace: bool },
+    Parse,
+    Check,
+    Repl,
+}
+
+impl Command {
+    fn from_arg(arg: &str) -> Option<Self> {
+        match arg {
+            "run" => Some(Command::Run { trace: false }),
+            "parse" => Some(Command::Parse),
+            "check" => Some(Command::Check),
+            "repl" => Some(Command::Repl),
+            _ => None,
+        }
+    }
+}
+
+fn read_source(path: Option<&str>) -> io::Result<String> {
+    match path {
+        None | Some("-") => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input)?;
+            Ok(input)
+        }
+        Some(path) => fs::read_to_string(path),
+    }
+}
+
+fn execute(command: Command, input: &str) -> ExitCode {
+    let program = match parser::parse_ast(input) {
+        Ok(program) => program,
+        Err(err) => {
+            eprintln!("parse error at {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match command {
+        Command::Run { trace } => {
+            if let Err(err) = runtime::evaluate_program(program, trace) {
+                eprintln!("runtime error: {}", err);
+                return ExitCode::FAILURE;
+            }
+        }
+        Command::Parse => print!("{}", program),
+        Command::Check => println!("ok: {} statements", program.body.len()),
+        Command::Repl => unreachable!("the repl does not execute a source file"),
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// Scripts recursing up to the call depth limit of the runtime need more stack than a main thread
+/// gets, so commands run on a thread of their own. The stack is only committed as it is used.
+const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    match thread::Builder::new().stack_size(STACK_SIZE).spawn(move || run(args)) {
+        // A panic has already been reported by the panic hook.
+        Ok(handle) => handle.join().unwrap_or(ExitCode::FAILURE),
+        Err(err) => {
+            eprintln!("cannot start the interpreter: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Vec<String>) -> ExitCode {
+    let mut command = match args.first().map(String::as_str) {
+        Some("-h") | Some("--help") => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Some(arg) => match Command::from_arg(arg) {
+            Some(command) => command,
+            None => {
+                eprintln!("unknown command '{}'\n\n{}", arg, USAGE);
+                return ExitCode::FAILURE;
+            }
         },
-        Err(err) => panic!("error parsing ast: {:?}", err)
+        None => {
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let Command::Repl = command {
+        return repl::start();
     }
+
+    let mut files = Vec::new();
+    for arg in &args[1..] {
+        match (arg.as_str(), &mut command) {
+            ("--trace", Command::Run { trace }) => *trace = true,
+            (option, _) if option.starts_with("--") => {
+                eprintln!("unknown option '{}'\n\n{}", option, USAGE);
+                return ExitCode::FAILURE;
+            }
+            (file, _) => files.push(file),
+        }
+    }
+
+    if files.len() > 1 {
+        eprintln!("expected a single file argument\n\n{}", USAGE);
+        return ExitCode::FAILURE;
+    }
+
+    let path = files.first().copied();
+    let input = match read_source(path) {
+        Ok(input) => input,
+        Err(err) => {
+            eprintln!("cannot read '{}': {}", path.unwrap_or("<stdin>"), err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    execute(command, &input)
 }
@@ -0,0 +1,123 @@
+use std::process::ExitCode;
+
+use rustyline::{error::ReadlineError, DefaultEditor};
+
+use crate::runtime::{self, environment::Environment, value_types::ValueType};
+
+const HELP: &str = "Meta-commands:
+    :ast <code>     print the AST of <code> without evaluating it
+    :env            print the variables of the current environment
+    :reset          discard every variable and start with a fresh environment
+    :history        print the inputs entered so far
+    :help           print this message
+    :quit           exit the repl";
+
+const PROMPT: &str = ">> ";
+const CONTINUATION_PROMPT: &str = ".. ";
+
+/// Reads lines until every bracket is closed. Returns `None` on end of input, Ctrl-C drops the
+/// lines read so far.
+fn read_input(editor: &mut DefaultEditor) -> rustyline::Result<Option<String>> {
+    let mut input = String::new();
+    let mut prompt = PROMPT;
+    loop {
+        match editor.readline(prompt) {
+            Ok(line) => {
+                input.push_str(&line);
+                input.push('\n');
+            }
+            Err(ReadlineError::Eof) if input.is_empty() => return Ok(None),
+            Err(ReadlineError::Eof) => return Ok(Some(input)),
+            Err(ReadlineError::Interrupted) => {
+                input.clear();
+                prompt = PROMPT;
+                continue;
+            }
+            Err(err) => return Err(err),
+        }
+
+        if parser::is_balanced(&input) {
+            return Ok(Some(input));
+        }
+
+        prompt = CONTINUATION_PROMPT;
+    }
+}
+
+fn print_ast(input: &str) {
+    match parser::parse_ast(input) {
+        Ok(program) => print!("{}", program),
+        Err(err) => eprintln!("parse error at {}", err),
+    }
+}
+
+fn evaluate_input(env: &mut Environment, input: &str) {
+    let program = match parser::parse_ast(input) {
+        Ok(program) => program,
+        Err(err) => {
+            eprintln!("parse error at {}", err);
+            return;
+        }
+    };
+
+    for statement in program.body {
+        match runtime::evaluate(env, &statement) {
+            Ok(value) if value.r#type == ValueType::None => {}
+            Ok(value) => println!("{}", value),
+            Err(err) => {
+                eprintln!("runtime error: {}", err);
+                return;
+            }
+        }
+    }
+}
+
+pub fn start() -> ExitCode {
+    println!("Codamus repl, type :help for the list of meta-commands");
+
+    let mut editor = match DefaultEditor::new() {
+        Ok(editor) => editor,
+        Err(err) => {
+            eprintln!("cannot start the line editor: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut env = runtime::global_environment();
+
+    loop {
+        let input = match read_input(&mut editor) {
+            Ok(Some(input)) => input,
+            Ok(None) => break,
+            Err(err) => {
+                eprintln!("cannot read input: {}", err);
+                return ExitCode::FAILURE;
+            }
+        };
+
+        let trimmed = input.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        // The up and down arrows recall earlier inputs, a multi-line input is recalled whole.
+        if let Err(err) = editor.add_history_entry(trimmed) {
+            eprintln!("cannot add the input to the history: {}", err);
+        }
+
+        match trimmed.split_once(char::is_whitespace).unwrap_or((trimmed, "")) {
+            (":quit", _) | (":q", _) => break,
+            (":help", _) => println!("{}", HELP),
+            (":ast", code) => print_ast(code),
+            (":env", _) => print!("{}", env),
+            (":reset", _) => env = runtime::global_environment(),
+            (":history", _) => {
+                for (i, entry) in editor.history().into_iter().enumerate() {
+                    println!("{:>4}  {}", i + 1, entry);
+                }
+            }
+            (command, _) if command.starts_with(':') => eprintln!("unknown meta-command '{}', type :help", command),
+            _ => evaluate_input(&mut env, trimmed),
+        }
+    }
+
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,202 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use super::{
+    environment::Environment,
+    error::RuntimeError,
+    heap::{self, Gc},
+    value_types::{NativeFunction, ValueType},
+    RuntimeValue,
+};
+
+const BUILTINS: [NativeFunction; 9] = [
+    NativeFunction { name: "print", call: print },
+    NativeFunction { name: "len", call: len },
+    NativeFunction { name: "keys", call: keys },
+    NativeFunction { name: "int", call: int },
+    NativeFunction { name: "float", call: float },
+    NativeFunction { name: "equals", call: equals },
+    NativeFunction { name: "freeze", call: freeze },
+    NativeFunction { name: "is_frozen", call: is_frozen },
+    NativeFunction { name: "gc", call: gc },
+];
+
+fn print(args: Vec<RuntimeValue>) -> Result<RuntimeValue, RuntimeError> {
+    let args: Vec<String> = args.iter().map(RuntimeValue::to_string).collect();
+    println!("{}", args.join(" "));
+
+    Ok(RuntimeValue { r#type: ValueType::None })
+}
+
+fn len(args: Vec<RuntimeValue>) -> Result<RuntimeValue, RuntimeError> {
+    let length = match args.as_slice() {
+        [RuntimeValue { r#type: ValueType::Array(items) }] => items.borrow().len(),
+        [RuntimeValue { r#type: ValueType::Object(properties) }] => properties.borrow().len(),
+        [RuntimeValue { r#type: ValueType::String(val) }] => val.borrow().chars().count(),
+        [val] => {
+            return Err(RuntimeError::TypeMismatch(format!(
+                "Cannot take the length of {} '{}'",
+                val.r#type.type_name(),
+                val
+            )))
+        }
+        _ => {
+            return Err(RuntimeError::ArityMismatch {
+                name: "len".to_owned(),
+                expected: 1,
+                found: args.len(),
+            })
+        }
+    };
+
+    Ok(RuntimeValue {
+        r#type: ValueType::Int(length as i64),
+    })
+}
+
+/// `keys(obj)` returns the keys of an object in the order `for (x in obj)` visits its values.
+fn keys(args: Vec<RuntimeValue>) -> Result<RuntimeValue, RuntimeError> {
+    let keys = match single_argument("keys", args)? {
+        ValueType::Object(properties) => properties
+            .borrow()
+            .keys()
+            .map(|key| RuntimeValue {
+                r#type: ValueType::string(key.clone()),
+            })
+            .collect(),
+        val => return Err(RuntimeError::TypeMismatch(format!("Cannot take the keys of {} '{}'", val.type_name(), val))),
+    };
+
+    Ok(RuntimeValue {
+        r#type: ValueType::Array(Gc::new(keys)),
+    })
+}
+
+/// The only argument of a builtin taking exactly one.
+fn single_argument(name: &str, args: Vec<RuntimeValue>) -> Result<ValueType, RuntimeError> {
+    match <[RuntimeValue; 1]>::try_from(args) {
+        Ok([arg]) => Ok(arg.r#type),
+        Err(args) => Err(RuntimeError::ArityMismatch {
+            name: name.to_owned(),
+            expected: 1,
+            found: args.len(),
+        }),
+    }
+}
+
+fn conversion_error(val: &ValueType, target: &str) -> RuntimeError {
+    RuntimeError::TypeMismatch(format!("Cannot convert {} '{}' to {}", val.type_name(), val, target))
+}
+
+/// `int(value)` converts a float, truncating its fraction, or parses a string.
+fn int(args: Vec<RuntimeValue>) -> Result<RuntimeValue, RuntimeError> {
+    let val = single_argument("int", args)?;
+    let result = match val {
+        ValueType::Int(x) => Some(x),
+        // `as` saturates, so the range is checked first.
+        ValueType::Float(x) if x.is_finite() && x >= i64::MIN as f64 && x < i64::MAX as f64 => Some(x as i64),
+        ValueType::String(ref val) => val.borrow().trim().parse().ok(),
+        _ => None,
+    };
+
+    match result {
+        Some(x) => Ok(RuntimeValue { r#type: ValueType::Int(x) }),
+        None => Err(conversion_error(&val, "an int")),
+    }
+}
+
+/// `float(value)` converts an int or parses a string.
+fn float(args: Vec<RuntimeValue>) -> Result<RuntimeValue, RuntimeError> {
+    let val = single_argument("float", args)?;
+    let result = match val {
+        ValueType::String(ref val) => val.borrow().trim().parse().ok(),
+        ref val => val.as_float(),
+    };
+
+    match result {
+        Some(x) => Ok(RuntimeValue { r#type: ValueType::Float(x) }),
+        None => Err(conversion_error(&val, "a float")),
+    }
+}
+
+/// `equals(a, b)` compares arrays and objects by what they hold rather than by identity like `==`.
+fn equals(args: Vec<RuntimeValue>) -> Result<RuntimeValue, RuntimeError> {
+    match <[RuntimeValue; 2]>::try_from(args) {
+        Ok([a, b]) => Ok(RuntimeValue {
+            r#type: ValueType::Bool(a.r#type.equals(&b.r#type)),
+        }),
+        Err(args) => Err(RuntimeError::ArityMismatch {
+            name: "equals".to_owned(),
+            expected: 2,
+            found: args.len(),
+        }),
+    }
+}
+
+/// `freeze(value)` freezes an array or object and everything inside it, then returns it.
+fn freeze(args: Vec<RuntimeValue>) -> Result<RuntimeValue, RuntimeError> {
+    let val = single_argument("freeze", args)?;
+    val.freeze();
+
+    Ok(RuntimeValue { r#type: val })
+}
+
+/// `is_frozen(value)` is false only for an array or object that can still be changed.
+fn is_frozen(args: Vec<RuntimeValue>) -> Result<RuntimeValue, RuntimeError> {
+    let val = single_argument("is_frozen", args)?;
+
+    Ok(RuntimeValue {
+        r#type: ValueType::Bool(val.is_frozen()),
+    })
+}
+
+/// `gc()` runs a collection and returns the heap statistics: the number of values alive, of
+/// collections run and of values they freed, and the time spent in the last and in all collections.
+fn gc(args: Vec<RuntimeValue>) -> Result<RuntimeValue, RuntimeError> {
+    if !args.is_empty() {
+        return Err(RuntimeError::ArityMismatch {
+            name: "gc".to_owned(),
+            expected: 0,
+            found: args.len(),
+        });
+    }
+
+    heap::collect();
+    let stats = heap::stats();
+
+    let int = |x: usize| RuntimeValue {
+        r#type: ValueType::Int(x as i64),
+    };
+    let milliseconds = |pause: Duration| RuntimeValue {
+        r#type: ValueType::Float(pause.as_secs_f64() * 1000.0),
+    };
+    let properties = BTreeMap::from([
+        ("heap_size".to_owned(), int(stats.size)),
+        ("collections".to_owned(), int(stats.collections)),
+        ("freed".to_owned(), int(stats.freed)),
+        ("last_pause_ms".to_owned(), milliseconds(stats.last_pause)),
+        ("pause_ms".to_owned(), milliseconds(stats.total_pause)),
+    ]);
+
+    Ok(RuntimeValue {
+        r#type: ValueType::Object(Gc::new(properties)),
+    })
+}
+
+/// Creates the root environment holding the builtin functions. Scripts run in a child of it, so
+/// they can shadow a builtin with their own declaration.
+pub fn environment() -> Environment {
+    let mut env = Environment::new(None);
+
+    for builtin in BUILTINS {
+        // The environment is empty, so a builtin name cannot be declared twice.
+        let _ = env.declare_var(
+            builtin.name.to_owned(),
+            RuntimeValue {
+                r#type: ValueType::NativeFunction(builtin),
+            },
+            true,
+        );
+    }
+
+    env
+}
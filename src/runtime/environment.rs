@@ -1,62 +1,133 @@
-use std::collections::{BTreeMap, BTreeSet};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::{self, Display, Formatter},
+    mem,
+};
 
-use super::RuntimeValue;
+use super::{
+    error::RuntimeError,
+    heap::{Gc, HeapId, Trace},
+    RuntimeValue,
+};
 
-#[derive(Clone, Debug)]
-pub struct Environment {
-    parent: Option<Box<Environment>>,
+struct Scope {
+    parent: Option<Environment>,
     variables: BTreeMap<String, RuntimeValue>,
     constants: BTreeSet<String>,
 }
 
+impl Trace for Scope {
+    fn trace(&self, visit: &mut dyn FnMut(HeapId)) {
+        if let Some(parent) = &self.parent {
+            parent.trace(visit);
+        }
+        self.variables.values().for_each(|value| value.r#type.trace(visit));
+    }
+
+    fn clear(&mut self) {
+        drop(mem::take(&mut self.variables));
+        drop(self.parent.take());
+    }
+}
+
+/// A handle to a scope on the heap. Clones share the scope, so a child scope or a closure created
+/// from an environment sees every later write to its variables.
+#[derive(Clone)]
+pub struct Environment(Gc<Scope>);
+
+impl Trace for Environment {
+    fn trace(&self, visit: &mut dyn FnMut(HeapId)) {
+        visit(self.0.id());
+    }
+}
+
 impl Environment {
-    pub fn new(parent: Option<Box<Environment>>) -> Self {
-        Self {
+    pub fn new(parent: Option<Environment>) -> Self {
+        Self(Gc::new(Scope {
             parent,
             variables: BTreeMap::new(),
             constants: BTreeSet::new(),
-        }
+        }))
+    }
+
+    /// Runs `scope` in a new child scope, which is dropped afterwards unless a closure captured it.
+    pub fn with_child_scope<T>(&mut self, scope: impl FnOnce(&mut Environment) -> T) -> T {
+        let mut child = Environment::new(Some(self.clone()));
+        scope(&mut child)
     }
 
-    pub fn resolve(&mut self, name: String) -> &mut Self {
-        if self.variables.contains_key(&name) {
-            return self;
+    /// A new scope next to this one, with the same parent and a copy of its variables.
+    pub fn copy_scope(&self) -> Environment {
+        let scope = self.0.borrow();
+        let copy = Environment::new(scope.parent.clone());
+        {
+            let mut copy_scope = copy.0.borrow_mut();
+            copy_scope.variables = scope.variables.clone();
+            copy_scope.constants = scope.constants.clone();
         }
+        copy
+    }
+
+    /// The scope declaring `name`, searching from this scope outwards.
+    pub fn resolve(&self, name: &str) -> Result<Environment, RuntimeError> {
+        let mut env = self.clone();
+        loop {
+            if env.0.borrow().variables.contains_key(name) {
+                return Ok(env);
+            }
 
-        if let Some(parent) = &mut self.parent {
-            return parent.resolve(name);
-        } else {
-            panic!("Cannot resolve variable '{}' as it does not exist", name)
+            let parent = env.0.borrow().parent.clone();
+            match parent {
+                Some(parent) => env = parent,
+                None => return Err(RuntimeError::UndefinedVariable(name.to_owned())),
+            }
         }
     }
 
-    pub fn lookup_var(&mut self, name: String) -> RuntimeValue {
-        let env = self.resolve(name.clone());
-        return env.variables.get(&name).unwrap().clone()
+    pub fn lookup_var(&self, name: String) -> Result<RuntimeValue, RuntimeError> {
+        let env = self.resolve(&name)?;
+        let value = env.0.borrow().variables[&name].clone();
+        Ok(value)
     }
 
-    pub fn declare_var(&mut self, name: String, value: RuntimeValue, constant: bool) -> RuntimeValue {
-        if self.variables.contains_key(&name) {
-            panic!("Cannot declare variable '{}' as it is already defined", name)
+    /// Declares `name` in this scope. A variable of an outer scope with the same name is shadowed
+    /// until the scope ends, while declaring a name twice in the same scope, the top level of a
+    /// script included, is an error.
+    pub fn declare_var(&mut self, name: String, value: RuntimeValue, constant: bool) -> Result<RuntimeValue, RuntimeError> {
+        let mut scope = self.0.borrow_mut();
+        if scope.variables.contains_key(&name) {
+            return Err(RuntimeError::Redeclaration(name));
         }
 
-        self.variables.insert(name.clone(), value.clone());
+        scope.variables.insert(name.clone(), value.clone());
 
         if constant {
-            self.constants.insert(name);
+            scope.constants.insert(name);
         }
 
-        return value;
+        Ok(value)
     }
 
-    pub fn assign_var(&mut self, name: String, value: RuntimeValue) -> RuntimeValue {
-        let env = self.resolve(name.clone());
+    pub fn assign_var(&mut self, name: String, value: RuntimeValue) -> Result<RuntimeValue, RuntimeError> {
+        let env = self.resolve(&name)?;
+        let mut scope = env.0.borrow_mut();
 
-        if env.constants.contains(&name) {
-            panic!("Cannot reassign values to a constant variable '{}'", name)
+        if scope.constants.contains(&name) {
+            return Err(RuntimeError::ConstReassignment(name));
         }
 
-        env.variables.entry(name).and_modify(|v| *v = value.clone());
-        return value;
+        scope.variables.insert(name, value.clone());
+        Ok(value)
+    }
+}
+
+impl Display for Environment {
+    fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
+        let scope = self.0.borrow();
+        for (name, value) in &scope.variables {
+            let kind = if scope.constants.contains(name) { "const" } else { "let" };
+            writeln!(format, "{} {} = {}", kind, name, value)?;
+        }
+        Ok(())
     }
 }
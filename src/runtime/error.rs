@@ -0,0 +1,43 @@
+use std::fmt::{self, Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuntimeError {
+    UndefinedVariable(String),
+    Redeclaration(String),
+    ConstReassignment(String),
+    TypeMismatch(String),
+    DivisionByZero,
+    IntegerOverflow(String),
+    IndexOutOfBounds { index: i64, length: usize },
+    UndefinedProperty(String),
+    FrozenValue(String),
+    ArityMismatch { name: String, expected: usize, found: usize },
+    NoMatchingArm(String),
+    InvalidControlFlow(String),
+    StackOverflow { depth: usize },
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
+        use self::RuntimeError::*;
+        match self {
+            UndefinedVariable(name) => write!(format, "Cannot resolve variable '{}' as it does not exist", name),
+            Redeclaration(name) => write!(format, "Cannot declare variable '{}' as it is already defined", name),
+            ConstReassignment(name) => write!(format, "Cannot reassign values to a constant variable '{}'", name),
+            TypeMismatch(message) => write!(format, "{}", message),
+            DivisionByZero => write!(format, "Cannot divide by 0"),
+            IntegerOverflow(operation) => write!(format, "Integer overflow in '{}'", operation),
+            IndexOutOfBounds { index, length } => write!(format, "Index {} is out of bounds for an array of length {}", index, length),
+            UndefinedProperty(path) => write!(format, "Cannot access '{}' as the property does not exist", path),
+            FrozenValue(path) => write!(format, "Cannot assign to '{}' as the value is frozen", path),
+            ArityMismatch { name, expected, found } => {
+                write!(format, "Function '{}' expects {} arguments but {} were given", name, expected, found)
+            }
+            NoMatchingArm(value) => write!(format, "No arm of the match expression matches {}", value),
+            InvalidControlFlow(message) => write!(format, "{}", message),
+            StackOverflow { depth } => write!(format, "Stack overflow, functions cannot be nested more than {} calls deep", depth),
+        }
+    }
+}
+
+impl std::error::Error for RuntimeError {}
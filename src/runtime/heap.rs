@@ -0,0 +1,271 @@
+use std::{
+    cell::{Cell, Ref, RefCell, RefMut},
+    collections::{HashMap, HashSet},
+    fmt::{self, Debug, Formatter},
+    rc::{Rc, Weak},
+    time::{Duration, Instant},
+};
+
+/// Identifies a heap value while tracing, the address of its allocation.
+pub type HeapId = *const ();
+
+/// A value the collector can look into.
+pub trait Trace {
+    /// Calls `visit` with every heap value this value refers to directly.
+    fn trace(&self, visit: &mut dyn FnMut(HeapId));
+
+    /// Drops the heap values this value refers to. Called on the values found unreachable, which
+    /// breaks the reference cycles keeping them alive.
+    fn clear(&mut self) {}
+}
+
+struct GcBox<T: ?Sized> {
+    /// Set by `freeze`, a frozen value must not be changed anymore.
+    frozen: Cell<bool>,
+    value: RefCell<T>,
+}
+
+/// A handle to a value owned by the heap. Clones refer to the same value.
+pub struct Gc<T: Trace + 'static>(Rc<GcBox<T>>);
+
+impl<T: Trace + 'static> Gc<T> {
+    /// Moves `value` to the heap, running a collection first when the heap has grown enough since
+    /// the last one.
+    pub fn new(value: T) -> Self {
+        if HEAP.with(|heap| heap.borrow().should_collect()) {
+            collect();
+        }
+
+        let value = Rc::new(GcBox {
+            frozen: Cell::new(false),
+            value: RefCell::new(value),
+        });
+        let node: Weak<GcBox<dyn Trace>> = Rc::downgrade(&value) as Weak<GcBox<dyn Trace>>;
+        HEAP.with(|heap| heap.borrow_mut().values.push(node));
+        Self(value)
+    }
+
+    pub fn borrow(&self) -> Ref<'_, T> {
+        self.0.value.borrow()
+    }
+
+    pub fn borrow_mut(&self) -> RefMut<'_, T> {
+        self.0.value.borrow_mut()
+    }
+
+    /// Marks the value as frozen. It is up to the owner of the handle to refuse changes to it.
+    pub fn freeze(&self) {
+        self.0.frozen.set(true);
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.0.frozen.get()
+    }
+
+    /// Whether both handles refer to the same value.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+
+    pub fn id(&self) -> HeapId {
+        Rc::as_ptr(&self.0) as HeapId
+    }
+
+    /// Runs `visit` on the value unless it is already being visited further up, as happens when
+    /// printing a value that contains itself. Returns `None` in that case.
+    pub fn visit<R>(&self, visit: impl FnOnce(&T) -> R) -> Option<R> {
+        if !VISITING.with(|visiting| visiting.borrow_mut().insert(self.id())) {
+            return None;
+        }
+
+        let result = visit(&self.borrow());
+        VISITING.with(|visiting| visiting.borrow_mut().remove(&self.id()));
+        Some(result)
+    }
+}
+
+impl<T: Trace + 'static> Clone for Gc<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T: Trace + Debug + 'static> Debug for Gc<T> {
+    fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
+        self.visit(|value| value.fmt(format)).unwrap_or_else(|| write!(format, "..."))
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct Stats {
+    /// The number of values alive on the heap.
+    pub size: usize,
+    pub collections: usize,
+    /// The number of values freed by collections, values outside of cycles are freed as soon as
+    /// their last handle is dropped and are not counted.
+    pub freed: usize,
+    pub last_pause: Duration,
+    pub total_pause: Duration,
+}
+
+/// Collections start once the heap holds this many values.
+const MIN_COLLECT_AT: usize = 1024;
+
+struct Heap {
+    values: Vec<Weak<GcBox<dyn Trace>>>,
+    /// A collection runs before the allocation that grows `values` past this length.
+    collect_at: usize,
+    stats: Stats,
+}
+
+impl Heap {
+    fn should_collect(&self) -> bool {
+        self.values.len() >= self.collect_at
+    }
+}
+
+thread_local! {
+    static VISITING: RefCell<HashSet<HeapId>> = RefCell::new(HashSet::new());
+    static HEAP: RefCell<Heap> = RefCell::new(Heap {
+        values: Vec::new(),
+        collect_at: MIN_COLLECT_AT,
+        stats: Stats::default(),
+    });
+}
+
+/// Frees the heap values that are only reachable from each other, returning how many were freed.
+///
+/// The roots are the values referenced from outside the heap: the environments of the running
+/// scopes and the values the interpreter holds while evaluating an expression. They are found by
+/// counting, for every value, the references coming from other heap values. A value with more
+/// handles than that is a root. Everything reachable from a root is marked, the rest is cleared.
+pub fn collect() -> usize {
+    let start = Instant::now();
+
+    let values: Vec<Rc<GcBox<dyn Trace>>> = HEAP.with(|heap| heap.borrow().values.iter().filter_map(Weak::upgrade).collect());
+    let index: HashMap<HeapId, usize> = values.iter().enumerate().map(|(i, value)| (Rc::as_ptr(value) as HeapId, i)).collect();
+
+    // A value being written to cannot be looked into, what it refers to is kept as a root instead.
+    let mut internal = vec![0; values.len()];
+    for value in &values {
+        if let Ok(value) = value.value.try_borrow() {
+            value.trace(&mut |id| {
+                if let Some(&i) = index.get(&id) {
+                    internal[i] += 1;
+                }
+            });
+        }
+    }
+
+    // `values` holds one handle to each value itself.
+    let mut pending: Vec<usize> = (0..values.len()).filter(|&i| Rc::strong_count(&values[i]) - 1 > internal[i]).collect();
+    let mut marked = vec![false; values.len()];
+    while let Some(i) = pending.pop() {
+        if marked[i] {
+            continue;
+        }
+        marked[i] = true;
+
+        if let Ok(value) = values[i].value.try_borrow() {
+            value.trace(&mut |id| {
+                if let Some(&i) = index.get(&id) {
+                    pending.push(i);
+                }
+            });
+        }
+    }
+
+    let mut freed = 0;
+    for (value, _) in values.iter().zip(&marked).filter(|(_, marked)| !**marked) {
+        if let Ok(mut value) = value.value.try_borrow_mut() {
+            value.clear();
+            freed += 1;
+        }
+    }
+    drop(values);
+
+    HEAP.with(|heap| {
+        let mut heap = heap.borrow_mut();
+        heap.values.retain(|value| value.strong_count() > 0);
+        heap.collect_at = (heap.values.len() * 2).max(MIN_COLLECT_AT);
+
+        let pause = start.elapsed();
+        heap.stats.collections += 1;
+        heap.stats.freed += freed;
+        heap.stats.last_pause = pause;
+        heap.stats.total_pause += pause;
+    });
+
+    freed
+}
+
+pub fn stats() -> Stats {
+    HEAP.with(|heap| {
+        let heap = heap.borrow();
+        Stats {
+            size: heap.values.iter().filter(|value| value.strong_count() > 0).count(),
+            ..heap.stats
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, rc::Rc};
+
+    use super::{collect, stats, Gc};
+    use crate::runtime::{environment::Environment, value_types::{Function, ValueType}, RuntimeValue};
+
+    fn value(r#type: ValueType) -> RuntimeValue {
+        RuntimeValue { r#type }
+    }
+
+    /// Declares `name` in `env` as a function closing over `env`, a scope and a function keeping
+    /// each other alive.
+    fn declare_closure(env: &mut Environment, name: &str) {
+        let function = Function {
+            name: name.to_owned(),
+            params: vec![],
+            body: Rc::new(vec![]),
+            env: env.clone(),
+        };
+        env.declare_var(name.to_owned(), value(ValueType::Function(Gc::new(function))), false).unwrap();
+    }
+
+    #[test]
+    fn frees_values_referring_to_themselves() {
+        // Tests can share the thread, and with it the heap, so what earlier ones left is freed first.
+        collect();
+        let size = stats().size;
+
+        let object = Gc::new(BTreeMap::new());
+        object.borrow_mut().insert("me".to_owned(), value(ValueType::Object(object.clone())));
+        let weak = Rc::downgrade(&object.0);
+        drop(object);
+
+        let mut env = Environment::new(None);
+        declare_closure(&mut env, "f");
+        drop(env);
+
+        assert_eq!(stats().size, size + 3);
+        assert_eq!(collect(), 3);
+        assert!(weak.upgrade().is_none());
+        assert_eq!(stats().size, size);
+    }
+
+    #[test]
+    fn keeps_values_reachable_from_a_live_environment() {
+        let mut env = Environment::new(None);
+        declare_closure(&mut env, "f");
+
+        let items = Gc::new(vec![value(ValueType::Int(1)), value(ValueType::Int(2))]);
+        env.declare_var("items".to_owned(), value(ValueType::Array(items)), false).unwrap();
+
+        assert_eq!(collect(), 0);
+        assert!(matches!(env.lookup_var("f".to_owned()).unwrap().r#type, ValueType::Function(_)));
+        match env.lookup_var("items".to_owned()).unwrap().r#type {
+            ValueType::Array(items) => assert_eq!(items.borrow().len(), 2),
+            other => panic!("expected the array, found {:?}", other),
+        }
+    }
+}
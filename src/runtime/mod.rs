@@ -1,116 +1,759 @@
+pub mod builtins;
 pub mod environment;
+pub mod error;
+pub mod heap;
 pub mod value_types;
 
-use std::collections::BTreeMap;
+use std::{
+    cell::Cell,
+    cmp::Ordering,
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+};
 
 use parser::{
-    ast::{ArithmeticExpression, Assign, Identifier, Oper, Statement, Object},
+    ast::{
+        Assign, CallExpression, CompoundAssign, ConditionalExpression, Expression, ForInStatement, ForStatement, FunctionDeclaration, IfStatement,
+        LogicalOper, MatchExpression, MemberAssign, MemberExpression, NumericLiteral, Object, Oper, Pattern, Statement, UnaryOper, UpdateExpression,
+        WhileStatement,
+    },
     Program,
 };
 
-use value_types::ValueType;
+use value_types::{Function, ValueType};
 
-use self::environment::Environment;
+use self::{environment::Environment, error::RuntimeError, heap::Gc};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct RuntimeValue {
     pub r#type: ValueType,
 }
 
-fn evaluate_numeric_arithmetic_expression(left: ValueType, right: ValueType, operator: Oper) -> ValueType {
+impl Display for RuntimeValue {
+    fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
+        write!(format, "{}", self.r#type)
+    }
+}
+
+/// Reasons for evaluation to stop before the end of a statement list. `Return` unwinds to the
+/// enclosing function call, which turns it back into a value, `Break` and `Continue` to the
+/// enclosing loop.
+enum Interrupt {
+    Error(RuntimeError),
+    Return(RuntimeValue),
+    Break,
+    Continue,
+}
+
+impl Interrupt {
+    /// Turns a control flow interrupt that escaped its function or program into an error.
+    fn into_error(self) -> RuntimeError {
+        match self {
+            Interrupt::Error(err) => err,
+            Interrupt::Return(_) => RuntimeError::InvalidControlFlow("Cannot return outside of a function".to_owned()),
+            Interrupt::Break => RuntimeError::InvalidControlFlow("Cannot break outside of a loop".to_owned()),
+            Interrupt::Continue => RuntimeError::InvalidControlFlow("Cannot continue outside of a loop".to_owned()),
+        }
+    }
+}
+
+impl From<RuntimeError> for Interrupt {
+    fn from(err: RuntimeError) -> Self {
+        Interrupt::Error(err)
+    }
+}
+
+type Evaluation = Result<RuntimeValue, Interrupt>;
+
+fn evaluate_binary_operation(left: ValueType, right: ValueType, operator: Oper) -> Result<ValueType, RuntimeError> {
     match operator {
         Oper::Add => left + right,
         Oper::Sub => left - right,
         Oper::Mul => left * right,
         Oper::Div => left / right,
+        Oper::FloorDiv => left.floor_div(right),
         Oper::Mod => left % right,
+        Oper::Pow => left.pow(right),
+        Oper::BitAnd => left & right,
+        Oper::BitOr => left | right,
+        Oper::BitXor => left ^ right,
+        Oper::Shl => left << right,
+        Oper::Shr => left >> right,
+        Oper::Eq => Ok(ValueType::Bool(left == right)),
+        Oper::NotEq => Ok(ValueType::Bool(left != right)),
+        Oper::StrictEq | Oper::Is => Ok(ValueType::Bool(left.is(&right))),
+        Oper::StrictNotEq => Ok(ValueType::Bool(!left.is(&right))),
+        Oper::Lt => Ok(ValueType::Bool(left.partial_compare(&right)? == Some(Ordering::Less))),
+        Oper::LtEq => Ok(ValueType::Bool(matches!(left.partial_compare(&right)?, Some(Ordering::Less | Ordering::Equal)))),
+        Oper::Gt => Ok(ValueType::Bool(left.partial_compare(&right)? == Some(Ordering::Greater))),
+        Oper::GtEq => Ok(ValueType::Bool(matches!(left.partial_compare(&right)?, Some(Ordering::Greater | Ordering::Equal)))),
     }
 }
 
-fn eval_left_right(env: &mut Environment, left: ArithmeticExpression, right: ArithmeticExpression, operator: Oper) -> RuntimeValue {
-    let left = evaluate(env, Statement::ArithmeticExpression(left));
-    let right = evaluate(env, Statement::ArithmeticExpression(right));
-
-    if matches!(left.r#type, ValueType::Number(_)) && matches!(left.r#type, ValueType::Number(_)) {
-        return RuntimeValue {
-            r#type: evaluate_numeric_arithmetic_expression(left.r#type.into(), right.r#type.into(), operator),
-        };
+fn evaluate_unary_operation(operator: UnaryOper, operand: ValueType) -> Result<ValueType, RuntimeError> {
+    match operator {
+        UnaryOper::Neg => -operand,
+        UnaryOper::Not => !operand,
+        UnaryOper::Plus => match operand {
+            ValueType::Int(_) | ValueType::Float(_) => Ok(operand),
+            val => Err(RuntimeError::TypeMismatch(format!("Cannot apply unary '+' to {} '{}'", val.type_name(), val))),
+        },
+        UnaryOper::BitNot => match operand {
+            ValueType::Int(x) => Ok(ValueType::Int(!x)),
+            val => Err(RuntimeError::TypeMismatch(format!("Cannot apply '~' to {} '{}'", val.type_name(), val))),
+        },
     }
+}
+
+fn eval_left_right(env: &mut Environment, left: &Expression, right: &Expression, operator: Oper) -> Evaluation {
+    let left = evaluate_expression(env, left)?;
+    let right = evaluate_expression(env, right)?;
 
-    return RuntimeValue { r#type: ValueType::None };
+    Ok(RuntimeValue {
+        r#type: evaluate_binary_operation(left.r#type, right.r#type, operator)?,
+    })
 }
 
-fn evaluate_arithmetic_expression(env: &mut Environment, expr: ArithmeticExpression) -> RuntimeValue {
+fn evaluate_expression(env: &mut Environment, expr: &Expression) -> Evaluation {
     match expr {
-        ArithmeticExpression::Value(val) => RuntimeValue {
-            r#type: ValueType::Number(val),
-        },
-        ArithmeticExpression::Identifier(id) => evaluate_identifier(env, id),
-        ArithmeticExpression::Add(left, right) => eval_left_right(env, *left, *right, Oper::Add),
-        ArithmeticExpression::Sub(left, right) => eval_left_right(env, *left, *right, Oper::Sub),
-        ArithmeticExpression::Mul(left, right) => eval_left_right(env, *left, *right, Oper::Mul),
-        ArithmeticExpression::Div(left, right) => eval_left_right(env, *left, *right, Oper::Div),
-        ArithmeticExpression::Mod(left, right) => eval_left_right(env, *left, *right, Oper::Mod),
-        ArithmeticExpression::Paren(_) => todo!(),
+        Expression::NumericLiteral(NumericLiteral::Int(val)) => Ok(RuntimeValue { r#type: ValueType::Int(*val) }),
+        Expression::NumericLiteral(NumericLiteral::Float(val)) => Ok(RuntimeValue { r#type: ValueType::Float(*val) }),
+        Expression::StringLiteral(val) => Ok(RuntimeValue {
+            r#type: ValueType::string(val.clone()),
+        }),
+        Expression::BooleanLiteral(val) => Ok(RuntimeValue { r#type: ValueType::Bool(*val) }),
+        Expression::NullLiteral => Ok(RuntimeValue { r#type: ValueType::None }),
+        Expression::Identifier(id) => Ok(env.lookup_var(id.id.clone())?),
+        Expression::ArrayLiteral(items) => evaluate_array_literal(env, items),
+        Expression::ObjectLiteral(obj) => evaluate_object_literal(env, obj),
+        Expression::Member(member) => evaluate_member_expression(env, member),
+        Expression::Call(call) => evaluate_call(env, call),
+        Expression::Update(update) => evaluate_update_expression(env, update),
+        Expression::Binary(left, operator, right) => eval_left_right(env, left, right, *operator),
+        Expression::Paren(expr) => evaluate_expression(env, expr),
+        Expression::Unary(operator, operand) => {
+            let operand = evaluate_expression(env, operand)?;
+            Ok(RuntimeValue {
+                r#type: evaluate_unary_operation(*operator, operand.r#type)?,
+            })
+        }
+        Expression::Logical(left, operator, right) => evaluate_logical_expression(env, left, *operator, right),
+        Expression::Conditional(conditional) => evaluate_conditional_expression(env, conditional),
+        Expression::Match(expr) => evaluate_match_expression(env, expr),
+    }
+}
+
+/// `&&` and `||` take bools, their right side is only evaluated when the left side does not
+/// already decide the result. `??` only evaluates its right side when the left side is `null`.
+fn evaluate_logical_expression(env: &mut Environment, left: &Expression, operator: LogicalOper, right: &Expression) -> Evaluation {
+    let left = evaluate_expression(env, left)?;
+
+    let operand = |value: RuntimeValue| match value.r#type {
+        ValueType::Bool(val) => Ok(val),
+        val => Err(RuntimeError::TypeMismatch(format!(
+            "Operands of '{}' must be bools, found {} '{}'",
+            operator,
+            val.type_name(),
+            val
+        ))),
+    };
+
+    let decided = match operator {
+        LogicalOper::And => !operand(left)?,
+        LogicalOper::Or => operand(left)?,
+        LogicalOper::Coalesce if matches!(left.r#type, ValueType::None) => return evaluate_expression(env, right),
+        LogicalOper::Coalesce => return Ok(left),
+    };
+    if decided {
+        return Ok(RuntimeValue {
+            r#type: ValueType::Bool(operator == LogicalOper::Or),
+        });
+    }
+
+    let right = evaluate_expression(env, right)?;
+    Ok(RuntimeValue {
+        r#type: ValueType::Bool(operand(right)?),
+    })
+}
+
+fn evaluate_conditional_expression(env: &mut Environment, conditional: &ConditionalExpression) -> Evaluation {
+    if evaluate_condition(env, &conditional.condition)? {
+        evaluate_expression(env, &conditional.consequent)
+    } else {
+        evaluate_expression(env, &conditional.alternate)
+    }
+}
+
+/// Evaluates the body of the first arm with a pattern matching the value. A binding pattern
+/// declares the value in a scope of its own for the body.
+fn evaluate_match_expression(env: &mut Environment, expr: &MatchExpression) -> Evaluation {
+    let value = evaluate_expression(env, &expr.value)?;
+
+    for arm in &expr.arms {
+        for pattern in &arm.patterns {
+            match pattern {
+                Pattern::Wildcard => return evaluate_expression(env, &arm.body),
+                Pattern::Binding(name) => {
+                    return env.with_child_scope(|env| {
+                        env.declare_var(name.clone(), value, false)?;
+                        evaluate_expression(env, &arm.body)
+                    })
+                }
+                Pattern::Literal(literal) => {
+                    if evaluate_expression(env, literal)? == value {
+                        return evaluate_expression(env, &arm.body);
+                    }
+                }
+            }
+        }
     }
+
+    Err(RuntimeError::NoMatchingArm(format!("{} '{}'", value.r#type.type_name(), value)).into())
 }
 
-fn evaluate_identifier(env: &mut Environment, id: Identifier) -> RuntimeValue {
-    return env.lookup_var(id.id);
+fn evaluate_array_literal(env: &mut Environment, items: &[Expression]) -> Evaluation {
+    let mut array = Vec::with_capacity(items.len());
+    for item in items {
+        array.push(evaluate_expression(env, item)?);
+    }
+
+    Ok(RuntimeValue {
+        r#type: ValueType::Array(Gc::new(array)),
+    })
 }
 
-fn evaluate_object_literal(env: &mut Environment, obj: Object) -> RuntimeValue {
+fn evaluate_object_literal(env: &mut Environment, obj: &Object) -> Evaluation {
     let mut object: BTreeMap<String, RuntimeValue> = BTreeMap::new();
-    
-    for prop in obj.properties {
-        match prop.value {
-            Some(statement) => object.insert(prop.key.to_owned(), evaluate(env, *statement)),
-            None => object.insert(prop.key.to_owned(), env.lookup_var(prop.key.to_owned())),
+
+    for prop in &obj.properties {
+        match &prop.value {
+            Some(expr) => object.insert(prop.key.clone(), evaluate_expression(env, expr)?),
+            None => object.insert(prop.key.clone(), env.lookup_var(prop.key.clone())?),
         };
     }
 
-    RuntimeValue { r#type: ValueType::Object(object) }
+    Ok(RuntimeValue {
+        r#type: ValueType::Object(Gc::new(object)),
+    })
 }
 
-fn evaluate_declaration(env: &mut Environment, assign: Assign) -> RuntimeValue {
-    let expr = evaluate(env, *assign.expression);
-    return env.declare_var(assign.id.to_owned(), expr, assign.constant);
+/// The key a member expression reads, the evaluated property for `obj[expr]` and the name for `obj.name`.
+fn member_key(env: &mut Environment, member: &MemberExpression) -> Result<ValueType, Interrupt> {
+    match &*member.property {
+        Expression::Identifier(id) if !member.computed => Ok(ValueType::string(id.id.clone())),
+        property => Ok(evaluate_expression(env, property)?.r#type),
+    }
 }
 
-fn evaluate_assign(env: &mut Environment, assign: Assign) -> RuntimeValue {
-    let expr = evaluate(env, *assign.expression);
-    return env.assign_var(assign.id.to_owned(), expr);
+// A missing key or a write to a frozen value is reported with the whole expression, e.g.
+// `obj.complex.foo` rather than `foo`.
+fn with_member_path(err: RuntimeError, member: &MemberExpression) -> RuntimeError {
+    match err {
+        RuntimeError::UndefinedProperty(_) => RuntimeError::UndefinedProperty(member.to_string()),
+        RuntimeError::FrozenValue(_) => RuntimeError::FrozenValue(member.to_string()),
+        err => err,
+    }
 }
 
-fn evaluate(env: &mut Environment, ast_node: Statement) -> RuntimeValue {
-    match ast_node {
-        Statement::Comment(_) => RuntimeValue { r#type: ValueType::None },
-        Statement::BooleanLiteral(val) => RuntimeValue {
-            r#type: ValueType::Bool(val),
-        },
-        Statement::Identifier(id) => evaluate_identifier(env, id),
-        Statement::NumericLiteral(val) => RuntimeValue {
-            r#type: ValueType::Number(val.value),
+fn evaluate_member_expression(env: &mut Environment, member: &MemberExpression) -> Evaluation {
+    let object = evaluate_expression(env, &member.object)?;
+    let key = member_key(env, member)?;
+
+    Ok(object.r#type.index(key).map_err(|err| with_member_path(err, member))?)
+}
+
+/// The value of a `const` is frozen, see `ValueType::freeze`, so neither the variable nor what it
+/// holds can change.
+fn evaluate_declaration(env: &mut Environment, assign: &Assign) -> Evaluation {
+    let expr = evaluate_expression(env, &assign.expression)?;
+    if assign.constant {
+        expr.r#type.freeze();
+    }
+    Ok(env.declare_var(assign.id.clone(), expr, assign.constant)?)
+}
+
+fn evaluate_assign(env: &mut Environment, assign: &Assign) -> Evaluation {
+    let expr = evaluate_expression(env, &assign.expression)?;
+    Ok(env.assign_var(assign.id.clone(), expr)?)
+}
+
+/// The variable a member expression starts from and the keys leading to the member, `a.b[0].c` is
+/// `a` followed by `b`, `0` and `c`. Each key keeps the member expression it belongs to for errors.
+struct MemberPath<'a> {
+    id: String,
+    keys: Vec<(ValueType, &'a MemberExpression)>,
+}
+
+fn member_path<'a>(env: &mut Environment, target: &'a MemberExpression) -> Result<MemberPath<'a>, Interrupt> {
+    let mut members = vec![target];
+    let id = loop {
+        let member: &MemberExpression = members[members.len() - 1];
+        let mut object = &*member.object;
+        while let Expression::Paren(inner) = object {
+            object = inner;
+        }
+
+        match object {
+            Expression::Member(member) => members.push(member),
+            Expression::Identifier(id) => break id.id.clone(),
+            object => return Err(RuntimeError::TypeMismatch(format!("Cannot assign to a property of '{}'", object)).into()),
+        }
+    };
+    members.reverse();
+
+    let mut keys = Vec::with_capacity(members.len());
+    for member in members {
+        keys.push((member_key(env, member)?, member));
+    }
+
+    Ok(MemberPath { id, keys })
+}
+
+/// Writes `value` to the member at the end of `path`, every member before it must exist and the
+/// array or object written to must not be frozen.
+fn set_member(env: &Environment, path: &MemberPath, value: RuntimeValue) -> Result<(), RuntimeError> {
+    let (last, keys) = path.keys.split_last().expect("a member path has at least one key");
+
+    // Reading an array or object gives the one stored in the variable, so the write lands there.
+    let mut target = env.lookup_var(path.id.clone())?;
+    for (key, member) in keys {
+        target = target.r#type.index(key.clone()).map_err(|err| with_member_path(err, member))?;
+    }
+    target.r#type.set_index(last.0.clone(), value).map_err(|err| with_member_path(err, last.1))
+}
+
+fn evaluate_member_assign(env: &mut Environment, assign: &MemberAssign) -> Evaluation {
+    let path = member_path(env, &assign.target)?;
+    let value = evaluate_expression(env, &assign.expression)?;
+
+    set_member(env, &path, value.clone())?;
+    Ok(value)
+}
+
+/// Replaces the variable or member `target` with `update(current value)`, returning the value
+/// before and after the update. The keys of a member target are only evaluated once.
+fn update_target(
+    env: &mut Environment,
+    target: &Expression,
+    update: impl FnOnce(&mut Environment, ValueType) -> Result<ValueType, Interrupt>,
+) -> Result<(RuntimeValue, RuntimeValue), Interrupt> {
+    match target {
+        Expression::Identifier(id) => {
+            let current = env.lookup_var(id.id.clone())?;
+            let value = RuntimeValue {
+                r#type: update(env, current.r#type.clone())?,
+            };
+            env.assign_var(id.id.clone(), value.clone())?;
+            Ok((current, value))
+        }
+        Expression::Member(member) => {
+            let path = member_path(env, member)?;
+            let mut current = env.lookup_var(path.id.clone())?;
+            for (key, member) in &path.keys {
+                current = current.r#type.index(key.clone()).map_err(|err| with_member_path(err, member))?;
+            }
+            let value = RuntimeValue {
+                r#type: update(env, current.r#type.clone())?,
+            };
+            set_member(env, &path, value.clone())?;
+            Ok((current, value))
+        }
+        Expression::Paren(inner) => update_target(env, inner, update),
+        target => Err(RuntimeError::TypeMismatch(format!("Cannot assign to '{}'", target)).into()),
+    }
+}
+
+fn evaluate_compound_assign(env: &mut Environment, assign: &CompoundAssign) -> Evaluation {
+    let (_, value) = update_target(env, &assign.target, |env, current| {
+        let operand = evaluate_expression(env, &assign.expression)?;
+        Ok(evaluate_binary_operation(current, operand.r#type, assign.operator)?)
+    })?;
+
+    Ok(value)
+}
+
+fn evaluate_update_expression(env: &mut Environment, update: &UpdateExpression) -> Evaluation {
+    let (previous, value) = update_target(env, &update.target, |_, current| {
+        Ok(evaluate_binary_operation(current, ValueType::Int(1), update.operator)?)
+    })?;
+
+    Ok(if update.prefix { value } else { previous })
+}
+
+fn evaluate_function_declaration(env: &mut Environment, declaration: &FunctionDeclaration) -> Evaluation {
+    let function = Function {
+        name: declaration.name.clone(),
+        params: declaration.params.clone(),
+        body: declaration.body.clone(),
+        env: env.clone(),
+    };
+
+    Ok(env.declare_var(
+        declaration.name.clone(),
+        RuntimeValue {
+            r#type: ValueType::Function(Gc::new(function)),
         },
-        Statement::ObjectLiteral(val) => evaluate_object_literal(env, val),
+        false,
+    )?)
+}
+
+/// Calls nested deeper than this fail with `RuntimeError::StackOverflow` rather than overflowing
+/// the stack of the interpreter.
+const MAX_CALL_DEPTH: usize = 2_000;
+
+thread_local! {
+    static CALL_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+fn call_function(function: Function, args: Vec<RuntimeValue>) -> Evaluation {
+    if function.params.len() != args.len() {
+        return Err(RuntimeError::ArityMismatch {
+            name: function.name,
+            expected: function.params.len(),
+            found: args.len(),
+        }
+        .into());
+    }
+
+    let depth = CALL_DEPTH.get();
+    if depth >= MAX_CALL_DEPTH {
+        return Err(RuntimeError::StackOverflow { depth: MAX_CALL_DEPTH }.into());
+    }
+
+    CALL_DEPTH.set(depth + 1);
+    let result = execute_function_body(&function, args);
+    CALL_DEPTH.set(depth);
+    result
+}
+
+fn execute_function_body(function: &Function, args: Vec<RuntimeValue>) -> Evaluation {
+    // The captured environment is shared with the declaring scope, so it sees the function itself
+    // and every variable declared or assigned there after the declaration.
+    let mut scope = Environment::new(Some(function.env.clone()));
+    for (param, arg) in function.params.iter().zip(args) {
+        scope.declare_var(param.to_owned(), arg, false)?;
+    }
+
+    for statement in function.body.iter() {
+        match execute(&mut scope, statement) {
+            Ok(_) => {}
+            Err(Interrupt::Return(value)) => return Ok(value),
+            Err(err) => return Err(err.into_error().into()),
+        }
+    }
+
+    Ok(RuntimeValue { r#type: ValueType::None })
+}
+
+fn evaluate_call(env: &mut Environment, call: &CallExpression) -> Evaluation {
+    let callee = evaluate_expression(env, &call.callee)?;
+
+    let mut args = Vec::with_capacity(call.args.len());
+    for arg in &call.args {
+        args.push(evaluate_expression(env, arg)?);
+    }
+
+    match callee.r#type {
+        ValueType::Function(function) => {
+            let function = function.borrow().clone();
+            call_function(function, args)
+        }
+        ValueType::NativeFunction(function) => Ok((function.call)(args)?),
+        val => Err(RuntimeError::TypeMismatch(format!("{} '{}' is not a function", val.type_name(), val)).into()),
+    }
+}
+
+fn execute_statements(env: &mut Environment, statements: &[Statement]) -> Evaluation {
+    let mut last_evaluated = RuntimeValue { r#type: ValueType::None };
+    for statement in statements {
+        last_evaluated = execute(env, statement)?;
+    }
+    Ok(last_evaluated)
+}
+
+fn execute_block(env: &mut Environment, statements: &[Statement]) -> Evaluation {
+    env.with_child_scope(|scope| execute_statements(scope, statements))
+}
+
+fn evaluate_condition(env: &mut Environment, condition: &Expression) -> Result<bool, Interrupt> {
+    match evaluate_expression(env, condition)?.r#type {
+        ValueType::Bool(val) => Ok(val),
+        val => Err(RuntimeError::TypeMismatch(format!("Condition must be a bool, found {} '{}'", val.type_name(), val)).into()),
+    }
+}
+
+fn evaluate_if(env: &mut Environment, statement: &IfStatement) -> Evaluation {
+    if evaluate_condition(env, &statement.condition)? {
+        return execute_block(env, &statement.consequent);
+    }
+
+    match &statement.alternate {
+        Some(alternate) => execute_block(env, alternate),
+        None => Ok(RuntimeValue { r#type: ValueType::None }),
+    }
+}
+
+/// Runs one iteration of a loop body, returning `false` when the loop should stop.
+fn run_iteration(result: Evaluation) -> Result<bool, Interrupt> {
+    match result {
+        Ok(_) | Err(Interrupt::Continue) => Ok(true),
+        Err(Interrupt::Break) => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+fn evaluate_while(env: &mut Environment, statement: &WhileStatement) -> Evaluation {
+    while evaluate_condition(env, &statement.condition)? {
+        if !run_iteration(execute_block(env, &statement.body))? {
+            break;
+        }
+    }
+
+    Ok(RuntimeValue { r#type: ValueType::None })
+}
+
+fn evaluate_for(env: &mut Environment, statement: &ForStatement) -> Evaluation {
+    // The loop variable lives in a scope around the body. Every iteration works on a copy of that
+    // scope, so a closure created in the body keeps the loop variable of its own iteration.
+    env.with_child_scope(|env| {
+        if let Some(init) = &statement.init {
+            execute(env, init)?;
+        }
+
+        let mut header = env.clone();
+        loop {
+            if let Some(condition) = &statement.condition {
+                if !evaluate_condition(&mut header, condition)? {
+                    break;
+                }
+            }
+
+            if !run_iteration(execute_block(&mut header, &statement.body))? {
+                break;
+            }
+
+            header = header.copy_scope();
+            if let Some(update) = &statement.update {
+                execute(&mut header, update)?;
+            }
+        }
+
+        Ok(RuntimeValue { r#type: ValueType::None })
+    })
+}
+
+fn evaluate_for_in(env: &mut Environment, statement: &ForInStatement) -> Evaluation {
+    let iterable = evaluate_expression(env, &statement.iterable)?;
+
+    for item in iterable.r#type.iterate()? {
+        let iteration = env.with_child_scope(|scope| {
+            scope.declare_var(statement.id.clone(), item, false)?;
+            execute_statements(scope, &statement.body)
+        });
+
+        if !run_iteration(iteration)? {
+            break;
+        }
+    }
+
+    Ok(RuntimeValue { r#type: ValueType::None })
+}
+
+fn evaluate_return(env: &mut Environment, value: &Option<Expression>) -> Evaluation {
+    let value = match value {
+        Some(expr) => evaluate_expression(env, expr)?,
+        None => RuntimeValue { r#type: ValueType::None },
+    };
+
+    Err(Interrupt::Return(value))
+}
+
+fn execute(env: &mut Environment, ast_node: &Statement) -> Evaluation {
+    match ast_node {
+        Statement::Comment(_) => Ok(RuntimeValue { r#type: ValueType::None }),
+        Statement::Expression(expr) => evaluate_expression(env, expr),
         Statement::Declaration(assign) => evaluate_declaration(env, assign),
         Statement::Assign(assign) => evaluate_assign(env, assign),
-        Statement::ArithmeticExpression(expr) => evaluate_arithmetic_expression(env, expr),
-        // Statement::CallExpression(_) => todo!(),
-        _ => todo!("Need to implement AST node type evaluation: {}", ast_node),
+        Statement::MemberAssign(assign) => evaluate_member_assign(env, assign),
+        Statement::CompoundAssign(assign) => evaluate_compound_assign(env, assign),
+        Statement::FunctionDeclaration(declaration) => evaluate_function_declaration(env, declaration),
+        Statement::Return(value) => evaluate_return(env, value),
+        Statement::Block(statements) => execute_block(env, statements),
+        Statement::If(statement) => evaluate_if(env, statement),
+        Statement::While(statement) => evaluate_while(env, statement),
+        Statement::For(statement) => evaluate_for(env, statement),
+        Statement::ForIn(statement) => evaluate_for_in(env, statement),
+        Statement::Break => Err(Interrupt::Break),
+        Statement::Continue => Err(Interrupt::Continue),
     }
 }
 
-pub fn evaluate_program(program: Program) -> RuntimeValue {
+pub fn evaluate(env: &mut Environment, ast_node: &Statement) -> Result<RuntimeValue, RuntimeError> {
+    execute(env, ast_node).map_err(Interrupt::into_error)
+}
+
+/// Creates the environment scripts are evaluated in, a child of the builtins.
+pub fn global_environment() -> Environment {
+    Environment::new(Some(builtins::environment()))
+}
+
+pub fn evaluate_program(program: Program, trace: bool) -> Result<RuntimeValue, RuntimeError> {
     let mut last_evaluated = RuntimeValue { r#type: ValueType::None };
 
-    let mut env = Environment::new(None);
+    let mut env = global_environment();
+
+    for statement in &program.body {
+        if trace {
+            println!("Statement {}", statement);
+        }
+        last_evaluated = evaluate(&mut env, statement)?;
+        if trace {
+            println!("  - runtime value: {:?}", last_evaluated);
+        }
+    }
+
+    Ok(last_evaluated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{error::RuntimeError, evaluate_program, value_types::ValueType};
+
+    /// Runs `source` and returns the value of its last statement.
+    fn run(source: &str) -> Result<ValueType, RuntimeError> {
+        let program = parser::parse_ast(source).expect("the source parses");
+        evaluate_program(program, false).map(|value| value.r#type)
+    }
+
+    #[test]
+    fn rounds_floor_division_towards_negative_infinity() {
+        assert_eq!(run("7 // 2;"), Ok(ValueType::Int(3)));
+        assert_eq!(run("-7 // 2;"), Ok(ValueType::Int(-4)));
+        assert_eq!(run("7 // -2;"), Ok(ValueType::Int(-4)));
+        assert_eq!(run("-7.0 // 2;"), Ok(ValueType::Float(-4.0)));
+    }
+
+    #[test]
+    fn keeps_the_sign_of_the_dividend_in_remainders() {
+        assert_eq!(run("-7 % 2;"), Ok(ValueType::Int(-1)));
+        assert_eq!(run("7 % -2;"), Ok(ValueType::Int(1)));
+        assert_eq!(run("-7.5 % 2;"), Ok(ValueType::Float(-1.5)));
+    }
+
+    #[test]
+    fn fails_on_int_overflow() {
+        assert_eq!(
+            run("9223372036854775807 + 1;"),
+            Err(RuntimeError::IntegerOverflow("9223372036854775807 + 1".to_owned()))
+        );
+        assert!(matches!(run("-9223372036854775807 - 2;"), Err(RuntimeError::IntegerOverflow(_))));
+        assert!(matches!(run("2 ** 63;"), Err(RuntimeError::IntegerOverflow(_))));
+        assert_eq!(run("-9223372036854775807 - 1;"), Ok(ValueType::Int(i64::MIN)));
+    }
+
+    #[test]
+    fn promotes_ints_mixed_with_floats() {
+        assert_eq!(run("1 + 0.5;"), Ok(ValueType::Float(1.5)));
+        assert_eq!(run("2 * 1.0;"), Ok(ValueType::Float(2.0)));
+        assert_eq!(run("2 + 3;"), Ok(ValueType::Int(5)));
+    }
 
-    for statement in program.body {
-        println!("Statement {}", statement);
-        last_evaluated = evaluate(&mut env, statement);
-        println!("  - runtime value: {:?}", last_evaluated);
+    #[test]
+    fn divides_into_a_float() {
+        assert_eq!(run("7 / 2;"), Ok(ValueType::Float(3.5)));
+        assert_eq!(run("4 / 2;"), Ok(ValueType::Float(2.0)));
+        assert_eq!(run("1 / 0;"), Err(RuntimeError::DivisionByZero));
     }
 
-    return last_evaluated;
+    #[test]
+    fn reads_hex_and_binary_literals_as_bit_patterns() {
+        assert_eq!(run("0xFFFFFFFFFFFFFFFF;"), Ok(ValueType::Int(-1)));
+        assert_eq!(run("0x8000000000000000;"), Ok(ValueType::Int(i64::MIN)));
+        assert_eq!(run("0b1 << 63 == 0x8000000000000000;"), Ok(ValueType::Bool(true)));
+        assert_eq!(run("0x1234 & 0xFFFFFFFFFFFFFF00;"), Ok(ValueType::Int(0x1200)));
+        assert!(matches!(run("-0x8000000000000000;"), Err(RuntimeError::IntegerOverflow(_))));
+    }
+
+    #[test]
+    fn keeps_the_loop_variable_of_each_iteration_in_closures() {
+        let source = "
+            let getters = [null, null, null];
+            for (let i = 0; i < 3; i++) {
+                fn get() { return i; }
+                getters[i] = get;
+            }
+            getters[0]() * 100 + getters[1]() * 10 + getters[2]();";
+
+        assert_eq!(run(source), Ok(ValueType::Int(12)));
+    }
+
+    #[test]
+    fn carries_loop_variable_writes_to_the_next_iteration() {
+        let source = "
+            let iterations = 0;
+            for (let i = 0; i < 10; i++) {
+                i += 1;
+                iterations++;
+            }
+            iterations;";
+
+        assert_eq!(run(source), Ok(ValueType::Int(5)));
+    }
+
+    #[test]
+    fn evaluates_the_right_side_of_logical_operators_only_when_needed() {
+        let calls = |expression: &str| {
+            let source = format!("let calls = 0; fn right() {{ calls++; return true; }} let result = {}; calls;", expression);
+            run(&source)
+        };
+
+        assert_eq!(calls("true || right()"), Ok(ValueType::Int(0)));
+        assert_eq!(calls("false && right()"), Ok(ValueType::Int(0)));
+        assert_eq!(calls("1 ?? right()"), Ok(ValueType::Int(0)));
+        assert_eq!(calls("false || right()"), Ok(ValueType::Int(1)));
+        assert_eq!(calls("true && right()"), Ok(ValueType::Int(1)));
+        assert_eq!(calls("null ?? right()"), Ok(ValueType::Int(1)));
+    }
+
+    #[test]
+    fn names_the_whole_path_when_writing_to_a_frozen_value() {
+        let frozen = |path: &str| Err(RuntimeError::FrozenValue(path.to_owned()));
+
+        assert_eq!(run("const o = { a: { b: [1, { c: 2 }] } }; o.a.b[1].c = 3;"), frozen("o.a.b[1].c"));
+        assert_eq!(run("const o = { a: 1 }; o.a += 1;"), frozen("o.a"));
+        assert_eq!(run("let xs = freeze([1, [2]]); xs[1][0] = 3;"), frozen("xs[1][0]"));
+        // Other references to a frozen value see it frozen too.
+        assert_eq!(run("let inner = { c: 1 }; const o = { a: [inner] }; inner.c = 2;"), frozen("inner.c"));
+        assert_eq!(run("let o = { a: { b: 1 } }; o.a.b = 2; o.a.b;"), Ok(ValueType::Int(2)));
+    }
+
+    #[test]
+    fn shares_the_body_of_functions_from_one_declaration() {
+        let source = "
+            fn counter(start) {
+                fn next() { start++; return start; }
+                return next;
+            }
+            let a = counter(0);
+            let b = counter(10);
+            a();
+            [a == b, a(), b()];";
+
+        let ValueType::Array(values) = run(source).unwrap() else {
+            panic!("expected an array");
+        };
+        let values: Vec<ValueType> = values.borrow().iter().map(|value| value.r#type.clone()).collect();
+        assert_eq!(values, vec![ValueType::Bool(true), ValueType::Int(2), ValueType::Int(11)]);
+    }
+
+    #[test]
+    fn keeps_control_flow_from_escaping_functions() {
+        let invalid = |message: &str| Err(RuntimeError::InvalidControlFlow(message.to_owned()));
+
+        // A `break` in a function does not end the loop the function is called from.
+        assert_eq!(run("fn stop() { break; } while (true) { stop(); }"), invalid("Cannot break outside of a loop"));
+        assert_eq!(run("fn skip() { continue; } for (let i = 0; i < 2; i++) { skip(); }"), invalid("Cannot continue outside of a loop"));
+        assert_eq!(run("break;"), invalid("Cannot break outside of a loop"));
+        assert_eq!(run("if (true) { return 1; }"), invalid("Cannot return outside of a function"));
+    }
 }
@@ -1,86 +1,626 @@
-use std::{ops::{Add, Div, Mul, Rem, Sub}, collections::BTreeMap};
+use std::{
+    cmp::Ordering,
+    collections::BTreeMap,
+    fmt::{self, Debug, Display, Formatter},
+    mem,
+    ops::{Add, BitAnd, BitOr, BitXor, Div, Mul, Neg, Not, Rem, Shl, Shr, Sub},
+    rc::Rc,
+};
 
-use super::RuntimeValue;
+use parser::ast::Statement;
 
-#[derive(Debug, Clone, PartialEq)]
+use super::{
+    environment::Environment,
+    error::RuntimeError,
+    heap::{Gc, HeapId, Trace},
+    RuntimeValue,
+};
+
+#[derive(Clone)]
+pub struct Function {
+    pub name: String,
+    pub params: Vec<String>,
+    pub body: Rc<Vec<Statement>>,
+    /// The environment the function was declared in, which the body resolves free variables against.
+    pub env: Environment,
+}
+
+// Two function values are the same function only if they come from the same declaration, even
+// when they close over different scopes.
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.body, &other.body)
+    }
+}
+
+impl Trace for Function {
+    fn trace(&self, visit: &mut dyn FnMut(HeapId)) {
+        self.env.trace(visit);
+    }
+}
+
+impl Debug for Function {
+    fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
+        write!(format, "Function({})", self.name)
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct NativeFunction {
+    pub name: &'static str,
+    pub call: fn(Vec<RuntimeValue>) -> Result<RuntimeValue, RuntimeError>,
+}
+
+impl PartialEq for NativeFunction {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl Debug for NativeFunction {
+    fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
+        write!(format, "NativeFunction({})", self.name)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub enum ValueType {
     None,
-    Number(f64),
+    Int(i64),
+    Float(f64),
+    String(Gc<String>),
     Bool(bool),
-    Object(BTreeMap<String, RuntimeValue>),
+    Array(Gc<Vec<RuntimeValue>>),
+    Object(Gc<BTreeMap<String, RuntimeValue>>),
+    Function(Gc<Function>),
+    NativeFunction(NativeFunction),
 }
-impl Into<f64> for ValueType {
-    fn into(self) -> f64 {
-        match self {
-            ValueType::None => 0.0,
-            ValueType::Number(x) => x,
-            ValueType::Bool(_) => panic!("Bool cannot be cast into f64"),
-            _ => todo!()
+
+impl PartialEq for ValueType {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (ValueType::None, ValueType::None) => true,
+            (ValueType::Int(x), ValueType::Int(y)) => x == y,
+            (ValueType::Float(x), ValueType::Float(y)) => x == y,
+            // An int equals the float with the same value, `1 == 1.0`.
+            (ValueType::Int(x), ValueType::Float(y)) | (ValueType::Float(y), ValueType::Int(x)) => compare_int_float(*x, *y) == Some(Ordering::Equal),
+            (ValueType::String(x), ValueType::String(y)) => *x.borrow() == *y.borrow(),
+            (ValueType::Bool(x), ValueType::Bool(y)) => x == y,
+            // Arrays and objects are references, two of them are only equal when they are the same
+            // one. `equals` compares what they hold.
+            (ValueType::Array(x), ValueType::Array(y)) => x.ptr_eq(y),
+            (ValueType::Object(x), ValueType::Object(y)) => x.ptr_eq(y),
+            (ValueType::Function(x), ValueType::Function(y)) => *x.borrow() == *y.borrow(),
+            (ValueType::NativeFunction(x), ValueType::NativeFunction(y)) => x == y,
+            _ => false,
         }
     }
 }
 
-impl Add for ValueType {
-    type Output = Self;
+impl Trace for String {
+    fn trace(&self, _: &mut dyn FnMut(HeapId)) {}
+}
+
+impl Trace for Vec<RuntimeValue> {
+    fn trace(&self, visit: &mut dyn FnMut(HeapId)) {
+        self.iter().for_each(|item| item.r#type.trace(visit));
+    }
 
-    fn add(self, other: Self) -> Self {
+    fn clear(&mut self) {
+        drop(mem::take(self));
+    }
+}
+
+impl Trace for BTreeMap<String, RuntimeValue> {
+    fn trace(&self, visit: &mut dyn FnMut(HeapId)) {
+        self.values().for_each(|value| value.r#type.trace(visit));
+    }
+
+    fn clear(&mut self) {
+        drop(mem::take(self));
+    }
+}
+
+impl Trace for ValueType {
+    fn trace(&self, visit: &mut dyn FnMut(HeapId)) {
         match self {
-            ValueType::None => ValueType::None,
-            ValueType::Number(x) => ValueType::Number(x + <ValueType as Into<f64>>::into(other)),
-            ValueType::Bool(val) => panic!("Cannot Add bool '{val}' with number"),
-            _ => todo!()
+            ValueType::String(val) => visit(val.id()),
+            ValueType::Array(items) => visit(items.id()),
+            ValueType::Object(properties) => visit(properties.id()),
+            ValueType::Function(function) => visit(function.id()),
+            _ => {}
         }
     }
 }
-impl Sub for ValueType {
-    type Output = Self;
 
-    fn sub(self, other: Self) -> Self {
+impl ValueType {
+    pub fn string(val: String) -> Self {
+        ValueType::String(Gc::new(val))
+    }
+
+    /// `a === b` and `a is b`: both sides are the same array, object or function, or the same
+    /// value of the same type for everything else, so `1 == 1.0` but not `1 === 1.0`.
+    pub fn is(&self, other: &Self) -> bool {
+        match (self, other) {
+            (ValueType::Function(x), ValueType::Function(y)) => x.ptr_eq(y),
+            (ValueType::Int(_), ValueType::Float(_)) | (ValueType::Float(_), ValueType::Int(_)) => false,
+            (x, y) => x == y,
+        }
+    }
+
+    /// Freezes an array or object along with every array and object inside it. Other values cannot
+    /// be changed in the first place.
+    pub fn freeze(&self) {
         match self {
-            ValueType::None => ValueType::None,
-            ValueType::Number(x) => ValueType::Number(x - <ValueType as Into<f64>>::into(other)),
-            ValueType::Bool(val) => panic!("Cannot Subract bool '{val}' with number"),
-            _ => todo!()
+            ValueType::Array(items) if !items.is_frozen() => {
+                items.freeze();
+                items.borrow().iter().for_each(|item| item.r#type.freeze());
+            }
+            ValueType::Object(properties) if !properties.is_frozen() => {
+                properties.freeze();
+                properties.borrow().values().for_each(|value| value.r#type.freeze());
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether the value can no longer change, only arrays and objects can be unfrozen.
+    pub fn is_frozen(&self) -> bool {
+        match self {
+            ValueType::Array(items) => items.is_frozen(),
+            ValueType::Object(properties) => properties.is_frozen(),
+            _ => true,
         }
     }
-}
-impl Mul for ValueType {
-    type Output = Self;
 
-    fn mul(self, other: Self) -> Self {
+    /// Structural equality, used by `equals(a, b)`: arrays and objects are equal when they hold
+    /// equal values, other values when they are `==`.
+    pub fn equals(&self, other: &Self) -> bool {
+        structural_eq(self, other, &mut Vec::new())
+    }
+
+    pub fn type_name(&self) -> &'static str {
         match self {
-            ValueType::None => ValueType::None,
-            ValueType::Number(x) => ValueType::Number(x * <ValueType as Into<f64>>::into(other)),
-            ValueType::Bool(val) => panic!("Cannot Multiply bool '{val}' with number"),
-            _ => todo!()
+            ValueType::None => "null",
+            ValueType::Int(_) => "int",
+            ValueType::Float(_) => "float",
+            ValueType::String(_) => "string",
+            ValueType::Bool(_) => "bool",
+            ValueType::Array(_) => "array",
+            ValueType::Object(_) => "object",
+            ValueType::Function(_) | ValueType::NativeFunction(_) => "function",
+        }
+    }
+
+    /// Items visited by a `for (x in value)` loop, the items of an array, the values of an object in
+    /// key order or the characters of a string.
+    pub fn iterate(self) -> Result<Vec<RuntimeValue>, RuntimeError> {
+        match self {
+            ValueType::Array(items) => Ok(items.borrow().clone()),
+            ValueType::Object(properties) => Ok(properties.borrow().values().cloned().collect()),
+            ValueType::String(val) => Ok(val
+                .borrow()
+                .chars()
+                .map(|c| RuntimeValue {
+                    r#type: ValueType::string(c.to_string()),
+                })
+                .collect()),
+            val => Err(RuntimeError::TypeMismatch(format!("Cannot iterate over {} '{}'", val.type_name(), val))),
+        }
+    }
+
+    /// The value of an int or a float as a float, `None` for every other type.
+    pub fn as_float(&self) -> Option<f64> {
+        match *self {
+            ValueType::Int(x) => Some(x as f64),
+            ValueType::Float(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    /// Orders two values for `<`, `<=`, `>` and `>=`. Numbers are ordered by value and strings
+    /// lexicographically, `None` is returned when either side is `NaN`.
+    pub fn partial_compare(&self, other: &Self) -> Result<Option<Ordering>, RuntimeError> {
+        match (self, other) {
+            (ValueType::Int(x), ValueType::Int(y)) => Ok(Some(x.cmp(y))),
+            (ValueType::Int(x), ValueType::Float(y)) => Ok(compare_int_float(*x, *y)),
+            (ValueType::Float(x), ValueType::Int(y)) => Ok(compare_int_float(*y, *x).map(Ordering::reverse)),
+            (ValueType::String(x), ValueType::String(y)) => Ok(Some(x.borrow().cmp(&y.borrow()))),
+            (left, right) => match (left.as_float(), right.as_float()) {
+                (Some(x), Some(y)) => Ok(x.partial_cmp(&y)),
+                _ => Err(RuntimeError::TypeMismatch(format!(
+                    "Cannot compare {} '{}' with {} '{}'",
+                    left.type_name(),
+                    left,
+                    right.type_name(),
+                    right
+                ))),
+            },
+        }
+    }
+
+    /// `self // other`, a division rounded towards negative infinity. Two ints give an int.
+    pub fn floor_div(self, other: Self) -> Result<Self, RuntimeError> {
+        if is_zero(&other) {
+            return Err(RuntimeError::DivisionByZero);
+        }
+        numeric_operation("Floor divide", "//", self, other, floor_div_int, |x, y| (x / y).floor())
+    }
+
+    /// `self ** other`. An int raised to a non-negative int stays an int, a negative exponent
+    /// gives a float.
+    pub fn pow(self, other: Self) -> Result<Self, RuntimeError> {
+        match (self, other) {
+            (ValueType::Int(x), ValueType::Int(y)) if y < 0 => Ok(ValueType::Float((x as f64).powf(y as f64))),
+            (left, right) => numeric_operation(
+                "Raise",
+                "**",
+                left,
+                right,
+                |x, y| u32::try_from(y).ok().and_then(|y| x.checked_pow(y)),
+                f64::powf,
+            ),
+        }
+    }
+
+    /// Reads `value[index]`, an array is indexed by position and an object by key. An array or
+    /// object read this way is the one stored in `value`, not a copy.
+    pub fn index(&self, index: ValueType) -> Result<RuntimeValue, RuntimeError> {
+        match (self, index) {
+            (ValueType::Array(items), index) => {
+                let items = items.borrow();
+                let position = array_position(&index, items.len())?;
+                Ok(items[position].clone())
+            }
+            (ValueType::Object(properties), ValueType::String(key)) => {
+                let key = key.borrow();
+                properties.borrow().get(&*key).cloned().ok_or_else(|| RuntimeError::UndefinedProperty(key.clone()))
+            }
+            (val, index) => Err(index_mismatch(val, &index)),
+        }
+    }
+
+    /// Writes `value[index] = item`. Arrays only accept positions they already have, objects get
+    /// the key added when missing. Frozen arrays and objects refuse every write.
+    pub fn set_index(&self, index: ValueType, item: RuntimeValue) -> Result<(), RuntimeError> {
+        if matches!(self, ValueType::Array(_) | ValueType::Object(_)) && self.is_frozen() {
+            return Err(RuntimeError::FrozenValue(index.to_string()));
+        }
+
+        match (self, index) {
+            (ValueType::Object(properties), ValueType::String(key)) => {
+                properties.borrow_mut().insert(key.borrow().clone(), item);
+            }
+            (ValueType::Array(items), index) => {
+                let position = array_position(&index, items.borrow().len())?;
+                items.borrow_mut()[position] = item;
+            }
+            (val, index) => return Err(index_mismatch(val, &index)),
+        }
+        Ok(())
+    }
+}
+
+/// `compared` holds the pairs of arrays and objects being compared further up. Meeting one of them
+/// again means both sides repeat the same way, so the pair is equal as far as this path goes.
+fn structural_eq(x: &ValueType, y: &ValueType, compared: &mut Vec<(HeapId, HeapId)>) -> bool {
+    let pair = match (x, y) {
+        (ValueType::Array(a), ValueType::Array(b)) => (a.id(), b.id()),
+        (ValueType::Object(a), ValueType::Object(b)) => (a.id(), b.id()),
+        (x, y) => return x == y,
+    };
+    if pair.0 == pair.1 || compared.contains(&pair) {
+        return true;
+    }
+
+    compared.push(pair);
+    let equal = match (x, y) {
+        (ValueType::Array(a), ValueType::Array(b)) => {
+            let (a, b) = (a.borrow(), b.borrow());
+            a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| structural_eq(&a.r#type, &b.r#type, compared))
+        }
+        (ValueType::Object(a), ValueType::Object(b)) => {
+            let (a, b) = (a.borrow(), b.borrow());
+            a.len() == b.len()
+                && a.iter()
+                    .zip(b.iter())
+                    .all(|((key_a, a), (key_b, b))| key_a == key_b && structural_eq(&a.r#type, &b.r#type, compared))
         }
+        _ => unreachable!("both sides are arrays or objects"),
+    };
+    compared.pop();
+    equal
+}
+
+fn array_position(index: &ValueType, length: usize) -> Result<usize, RuntimeError> {
+    match *index {
+        ValueType::Int(x) if x >= 0 && (x as usize) < length => Ok(x as usize),
+        ValueType::Int(x) => Err(RuntimeError::IndexOutOfBounds { index: x, length }),
+        ref index => Err(RuntimeError::TypeMismatch(format!(
+            "Array index must be an int, found {} '{}'",
+            index.type_name(),
+            index
+        ))),
+    }
+}
+
+fn index_mismatch(val: &ValueType, index: &ValueType) -> RuntimeError {
+    RuntimeError::TypeMismatch(format!(
+        "Cannot index {} '{}' with {} '{}'",
+        val.type_name(),
+        val,
+        index.type_name(),
+        index
+    ))
+}
+
+/// Orders an int against a float without converting the int, which loses precision past 2^53.
+fn compare_int_float(x: i64, y: f64) -> Option<Ordering> {
+    // `i64::MAX as f64` rounds up to 2^63, which no int reaches.
+    if y.is_nan() {
+        None
+    } else if y >= i64::MAX as f64 {
+        Some(Ordering::Less)
+    } else if y < i64::MIN as f64 {
+        Some(Ordering::Greater)
+    } else {
+        // `y` is within the range of an int here, so its integral part converts exactly.
+        let integral = y.trunc();
+        Some(x.cmp(&(integral as i64)).then(integral.partial_cmp(&y)?))
+    }
+}
+
+// `0.0` equals the int `0`.
+fn is_zero(val: &ValueType) -> bool {
+    *val == ValueType::Int(0)
+}
+
+fn floor_div_int(x: i64, y: i64) -> Option<i64> {
+    let quotient = x.checked_div(y)?;
+    if x % y != 0 && (x < 0) != (y < 0) {
+        Some(quotient - 1)
+    } else {
+        Some(quotient)
+    }
+}
+
+/// Applies an arithmetic operator. Two ints give an int, failing on overflow, while an int mixed
+/// with a float is promoted to a float. Any other operand, `null` included, is a type error.
+fn numeric_operation(
+    operation: &str,
+    symbol: &str,
+    left: ValueType,
+    right: ValueType,
+    int: fn(i64, i64) -> Option<i64>,
+    float: fn(f64, f64) -> f64,
+) -> Result<ValueType, RuntimeError> {
+    match (left, right) {
+        (ValueType::Int(x), ValueType::Int(y)) => int(x, y)
+            .map(ValueType::Int)
+            .ok_or_else(|| RuntimeError::IntegerOverflow(format!("{} {} {}", x, symbol, y))),
+        (left, right) => match (left.as_float(), right.as_float()) {
+            (Some(x), Some(y)) => Ok(ValueType::Float(float(x, y))),
+            _ => Err(RuntimeError::TypeMismatch(format!(
+                "Cannot {} {} '{}' with {} '{}'",
+                operation,
+                left.type_name(),
+                left,
+                right.type_name(),
+                right
+            ))),
+        },
+    }
+}
+
+/// Applies a bitwise operator, which only takes ints.
+fn bitwise_operation(symbol: &str, left: ValueType, right: ValueType, apply: fn(i64, i64) -> Option<i64>) -> Result<ValueType, RuntimeError> {
+    match (left, right) {
+        (ValueType::Int(x), ValueType::Int(y)) => apply(x, y)
+            .map(ValueType::Int)
+            .ok_or_else(|| RuntimeError::IntegerOverflow(format!("{} {} {}", x, symbol, y))),
+        (left, right) => Err(RuntimeError::TypeMismatch(format!(
+            "Cannot apply '{}' to {} '{}' and {} '{}'",
+            symbol,
+            left.type_name(),
+            left,
+            right.type_name(),
+            right
+        ))),
+    }
+}
+
+impl Add for ValueType {
+    type Output = Result<Self, RuntimeError>;
+
+    fn add(self, other: Self) -> Self::Output {
+        match (self, other) {
+            (ValueType::String(x), ValueType::String(y)) => Ok(ValueType::string(format!("{}{}", x.borrow(), y.borrow()))),
+            (ValueType::String(x), right) => Err(RuntimeError::TypeMismatch(format!(
+                "Cannot concatenate string {:?} with {} '{}'",
+                x,
+                right.type_name(),
+                right
+            ))),
+            (left, right) => numeric_operation("Add", "+", left, right, i64::checked_add, |x, y| x + y),
+        }
+    }
+}
+impl Sub for ValueType {
+    type Output = Result<Self, RuntimeError>;
+
+    fn sub(self, other: Self) -> Self::Output {
+        numeric_operation("Subtract", "-", self, other, i64::checked_sub, |x, y| x - y)
+    }
+}
+impl Mul for ValueType {
+    type Output = Result<Self, RuntimeError>;
+
+    fn mul(self, other: Self) -> Self::Output {
+        numeric_operation("Multiply", "*", self, other, i64::checked_mul, |x, y| x * y)
     }
 }
 impl Div for ValueType {
-    type Output = Self;
+    type Output = Result<Self, RuntimeError>;
 
-    fn div(self, other: Self) -> Self {
-        let other_val = <ValueType as Into<f64>>::into(other);
-        if other_val == 0.0 {
-            panic!("Cannot divide by 0")
+    fn div(self, other: Self) -> Self::Output {
+        if is_zero(&other) {
+            return Err(RuntimeError::DivisionByZero);
         }
-        match self {
-            ValueType::None => ValueType::None,
-            ValueType::Number(x) => ValueType::Number(x / other_val),
-            ValueType::Bool(val) => panic!("Cannot Divide bool '{val}' with number"),
-            _ => todo!()
+        match (self, other) {
+            // `/` always divides exactly, `//` is the division that keeps ints.
+            (ValueType::Int(x), ValueType::Int(y)) => Ok(ValueType::Float(x as f64 / y as f64)),
+            (left, right) => numeric_operation("Divide", "/", left, right, i64::checked_div, |x, y| x / y),
         }
     }
 }
 impl Rem for ValueType {
-    type Output = Self;
+    type Output = Result<Self, RuntimeError>;
+
+    fn rem(self, other: Self) -> Self::Output {
+        if is_zero(&other) {
+            return Err(RuntimeError::DivisionByZero);
+        }
+        numeric_operation("Mod", "%", self, other, i64::checked_rem, |x, y| x % y)
+    }
+}
 
-    fn rem(self, other: Self) -> Self {
+impl BitAnd for ValueType {
+    type Output = Result<Self, RuntimeError>;
+
+    fn bitand(self, other: Self) -> Self::Output {
+        bitwise_operation("&", self, other, |x, y| Some(x & y))
+    }
+}
+impl BitOr for ValueType {
+    type Output = Result<Self, RuntimeError>;
+
+    fn bitor(self, other: Self) -> Self::Output {
+        bitwise_operation("|", self, other, |x, y| Some(x | y))
+    }
+}
+impl BitXor for ValueType {
+    type Output = Result<Self, RuntimeError>;
+
+    fn bitxor(self, other: Self) -> Self::Output {
+        bitwise_operation("^", self, other, |x, y| Some(x ^ y))
+    }
+}
+// Shifting by a negative amount or by 64 bits or more is an overflow.
+impl Shl for ValueType {
+    type Output = Result<Self, RuntimeError>;
+
+    fn shl(self, other: Self) -> Self::Output {
+        bitwise_operation("<<", self, other, |x, y| u32::try_from(y).ok().and_then(|y| x.checked_shl(y)))
+    }
+}
+impl Shr for ValueType {
+    type Output = Result<Self, RuntimeError>;
+
+    fn shr(self, other: Self) -> Self::Output {
+        bitwise_operation(">>", self, other, |x, y| u32::try_from(y).ok().and_then(|y| x.checked_shr(y)))
+    }
+}
+
+impl Neg for ValueType {
+    type Output = Result<Self, RuntimeError>;
+
+    fn neg(self) -> Self::Output {
+        match self {
+            ValueType::Int(x) => x
+                .checked_neg()
+                .map(ValueType::Int)
+                .ok_or_else(|| RuntimeError::IntegerOverflow(format!("-({})", x))),
+            ValueType::Float(x) => Ok(ValueType::Float(-x)),
+            val => Err(RuntimeError::TypeMismatch(format!("Cannot negate {} '{}'", val.type_name(), val))),
+        }
+    }
+}
+impl Not for ValueType {
+    type Output = Result<Self, RuntimeError>;
+
+    fn not(self) -> Self::Output {
+        match self {
+            ValueType::Bool(val) => Ok(ValueType::Bool(!val)),
+            val => Err(RuntimeError::TypeMismatch(format!("Cannot apply '!' to {} '{}'", val.type_name(), val))),
+        }
+    }
+}
+
+impl Display for ValueType {
+    fn fmt(&self, format: &mut Formatter<'_>) -> fmt::Result {
         match self {
-            ValueType::None => ValueType::None,
-            ValueType::Number(x) => ValueType::Number(x % <ValueType as Into<f64>>::into(other)),
-            ValueType::Bool(val) => panic!("Cannot Mod bool '{val}' with number"),
-            _ => todo!()
+            ValueType::None => write!(format, "null"),
+            ValueType::Int(val) => write!(format, "{}", val),
+            // Floats always show a fraction or an exponent, so `1.0` and `1` print differently.
+            ValueType::Float(val) => write!(format, "{:?}", val),
+            ValueType::String(val) => write!(format, "{}", val.borrow()),
+            ValueType::Bool(val) => write!(format, "{}", val),
+            // An array or object inside itself is printed as `[...]` or `{...}`.
+            ValueType::Array(items) => items
+                .visit(|items| {
+                    write!(format, "[")?;
+                    for (i, item) in items.iter().enumerate() {
+                        if i > 0 {
+                            write!(format, ", ")?;
+                        }
+                        write_nested(format, item)?;
+                    }
+                    write!(format, "]")
+                })
+                .unwrap_or_else(|| write!(format, "[...]")),
+            ValueType::Object(properties) => properties
+                .visit(|properties| {
+                    write!(format, "{{")?;
+                    for (i, (key, value)) in properties.iter().enumerate() {
+                        if i > 0 {
+                            write!(format, ",")?;
+                        }
+                        write!(format, " {}: ", key)?;
+                        write_nested(format, value)?;
+                    }
+                    write!(format, " }}")
+                })
+                .unwrap_or_else(|| write!(format, "{{...}}")),
+            ValueType::Function(function) => {
+                let function = function.borrow();
+                write!(format, "<fn {}({})>", function.name, function.params.join(", "))
+            }
+            ValueType::NativeFunction(function) => write!(format, "<native fn {}>", function.name),
         }
     }
 }
+
+// Strings inside arrays and objects are quoted, so `["1"]` and `[1]` print differently.
+fn write_nested(format: &mut Formatter<'_>, value: &RuntimeValue) -> fmt::Result {
+    match &value.r#type {
+        ValueType::String(val) => write!(format, "{:?}", val),
+        val => write!(format, "{}", val),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+
+    use super::ValueType::{self, Float, Int};
+
+    fn compare(left: ValueType, right: ValueType) -> Option<Ordering> {
+        left.partial_compare(&right).unwrap()
+    }
+
+    #[test]
+    fn compares_ints_with_floats_exactly() {
+        // 2^53 + 1 has no float, casting it would round it to 2^53.
+        assert_ne!(Int(9_007_199_254_740_993), Float(9_007_199_254_740_992.0));
+        assert_eq!(compare(Int(9_007_199_254_740_993), Float(9_007_199_254_740_992.0)), Some(Ordering::Greater));
+        assert_eq!(compare(Float(9_007_199_254_740_992.0), Int(9_007_199_254_740_993)), Some(Ordering::Less));
+
+        assert_eq!(Int(3), Float(3.0));
+        assert_eq!(compare(Int(3), Float(3.5)), Some(Ordering::Less));
+        assert_eq!(compare(Int(-3), Float(-3.5)), Some(Ordering::Greater));
+        assert_eq!(compare(Int(i64::MAX), Float(9_223_372_036_854_775_808.0)), Some(Ordering::Less));
+        assert_eq!(compare(Int(i64::MIN), Float(i64::MIN as f64)), Some(Ordering::Equal));
+        assert_eq!(compare(Int(i64::MIN), Float(f64::NEG_INFINITY)), Some(Ordering::Greater));
+        assert_eq!(compare(Int(0), Float(f64::NAN)), None);
+    }
+}